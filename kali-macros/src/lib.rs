@@ -13,12 +13,59 @@ pub fn entity(
     }
 }
 
+/// The arguments to `#[kali::entity(...)]`, an optional table name followed by flags.
+struct EntityArgs {
+    table_name: Option<syn::LitStr>,
+    // generate New{Entity}, which needs every inserted field to convert into a Value
+    insertable: bool,
+    // generate Active{Entity}, which needs every field but the key to be Clone and convert into a Value
+    active: bool,
+}
+
+impl syn::parse::Parse for EntityArgs {
+    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
+        let table_name = if input.peek(syn::LitStr) {
+            let table_name = input.parse()?;
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+
+            Some(table_name)
+        } else {
+            None
+        };
+
+        let mut args = EntityArgs {
+            table_name,
+            insertable: false,
+            active: false,
+        };
+
+        let flags = syn::punctuated::Punctuated::<Ident, syn::Token![,]>::parse_terminated(input)?;
+        for flag in flags {
+            if flag == "insertable" {
+                args.insertable = true;
+            } else if flag == "active" {
+                args.active = true;
+            } else {
+                return Err(syn::Error::new(
+                    flag.span(),
+                    "expected a table name, `insertable` or `active`",
+                ));
+            }
+        }
+
+        Ok(args)
+    }
+}
+
 #[derive(Clone)]
 enum Relation {
     ForeignKey {
         entity: Ident,
-        foreign_key_field: Ident,
-        references_field: Option<Ident>, // defaults to primary key
+        // several fields for a composite key, eg `foreign_key = (user_id, group_id)`
+        foreign_key_fields: Vec<Ident>,
+        references_fields: Option<Vec<Ident>>, // defaults to primary key
     },
     ReferencedBy {
         entity: Ident,
@@ -32,27 +79,27 @@ struct ParsedField {
     field_name: Ident,
     iden_name: Ident,
     is_pk: bool,
+    // left out of the generated insert struct, as the database fills them in
+    is_auto_increment: bool,
+    is_defaulted: bool,
     relation: Option<Relation>,
     raw: syn::Field,
 }
 
 // parse Collection<T> or Reference<T> to T
 fn parse_entity_from_type(entity: &syn::Type) -> Result<(bool, Ident), syn::Error> {
-    if let syn::Type::Path(type_path) = entity {
-        if let Some(segment) = type_path.path.segments.last() {
-            let is_collection = segment.ident == "Collection";
-            if is_collection || segment.ident == "Reference" {
-                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                    if let Some(syn::GenericArgument::Type(ty)) = args.args.first() {
-                        if let syn::Type::Path(type_path) = ty {
-                            return Ok((
-                                is_collection,
-                                type_path.path.segments.last().unwrap().ident.clone(),
-                            ));
-                        }
-                    }
-                }
-            }
+    if let syn::Type::Path(type_path) = entity
+        && let Some(segment) = type_path.path.segments.last()
+    {
+        let is_collection = segment.ident == "Collection";
+        if (is_collection || segment.ident == "Reference")
+            && let syn::PathArguments::AngleBracketed(args) = &segment.arguments
+            && let Some(syn::GenericArgument::Type(syn::Type::Path(type_path))) = args.args.first()
+        {
+            return Ok((
+                is_collection,
+                type_path.path.segments.last().unwrap().ident.clone(),
+            ));
         }
     }
     Err(syn::Error::new_spanned(
@@ -78,12 +125,20 @@ fn parse_fields(entity: syn::ItemStruct) -> Result<Vec<ParsedField>, syn::Error>
             );
 
             let mut is_pk = false;
+            let mut is_auto_increment = false;
+            let mut is_defaulted = false;
             let mut relation_attr = None;
 
             f.attrs.retain(|attr| {
                 if attr.path().is_ident("primary_key") {
                     is_pk = true;
                     false
+                } else if attr.path().is_ident("auto_increment") {
+                    is_auto_increment = true;
+                    false
+                } else if attr.path().is_ident("defaulted") {
+                    is_defaulted = true;
+                    false
                 } else if attr.path().is_ident("relation") {
                     relation_attr = Some(attr.clone());
                     false
@@ -111,18 +166,16 @@ fn parse_fields(entity: syn::ItemStruct) -> Result<Vec<ParsedField>, syn::Error>
                         referenced_by = Some(value.parse()?);
                         Ok(())
                     } else if meta.path.is_ident("foreign_key") {
-                        let value = meta.value()?;
-                        foreign_key = Some(value.parse()?);
+                        foreign_key = Some(parse_field_list(meta.value()?)?);
                         Ok(())
                     } else if meta.path.is_ident("references") {
-                        let value = meta.value()?;
-                        references = Some(value.parse()?);
+                        references = Some(parse_field_list(meta.value()?)?);
                         Ok(())
                     } else {
-                        return Err(syn::Error::new_spanned(
+                        Err(syn::Error::new_spanned(
                             &meta.path,
                             "expected `referenced_by` or `foreign_key` attribute",
-                        ));
+                        ))
                     }
                 })?;
 
@@ -130,15 +183,22 @@ fn parse_fields(entity: syn::ItemStruct) -> Result<Vec<ParsedField>, syn::Error>
                     (None, Some(fk), None) => {
                         Some(Relation::ForeignKey {
                             entity,
-                            foreign_key_field: fk,
-                            references_field: None,
+                            foreign_key_fields: fk,
+                            references_fields: None,
                         })
                     }
                     (None, Some(fk), Some(refs)) => {
+                        if fk.len() != refs.len() {
+                            return Err(syn::Error::new_spanned(
+                                &relation_attr,
+                                "expected as many `references` fields as `foreign_key` fields",
+                            ));
+                        }
+
                         Some(Relation::ForeignKey {
                             entity,
-                            foreign_key_field: fk,
-                            references_field: Some(refs),
+                            foreign_key_fields: fk,
+                            references_fields: Some(refs),
                         })
                     },
                     (Some(refs), None, None) => {
@@ -163,6 +223,8 @@ fn parse_fields(entity: syn::ItemStruct) -> Result<Vec<ParsedField>, syn::Error>
                 field_name,
                 iden_name,
                 is_pk,
+                is_auto_increment,
+                is_defaulted,
                 relation,
                 raw: f,
             })
@@ -173,6 +235,7 @@ fn parse_fields(entity: syn::ItemStruct) -> Result<Vec<ParsedField>, syn::Error>
 fn generate_entity_column_enum(
     vis: &syn::Visibility,
     entity_name: &syn::Ident,
+    table_name: &TokenStream,
     parsed_fields: &[ParsedField],
 ) -> syn::Result<(Ident, TokenStream)> {
     let col_enum_ident = Ident::new(&format!("{}Column", entity_name), entity_name.span());
@@ -199,11 +262,15 @@ fn generate_entity_column_enum(
         }
 
         impl kali::column::Column for #col_enum_ident {
-            fn to_col_name(&self) -> &str {
+            fn to_col_name(&self) -> &'static str {
                 match self {
                     #(#field_name_mappings),*
                 }
             }
+
+            fn table_name(&self) -> Option<&'static str> {
+                Some(#table_name)
+            }
         }
     };
 
@@ -214,7 +281,7 @@ fn generate_entity_constants(
     vis: &syn::Visibility,
     entity_enum_ident: &Ident,
     parsed_fields: &[ParsedField],
-    primary_key: &ParsedField,
+    primary_keys: &[&ParsedField],
 ) -> syn::Result<TokenStream> {
     let col_enum_variants = parsed_fields
         .iter()
@@ -232,11 +299,20 @@ fn generate_entity_constants(
         })
         .collect::<Vec<_>>();
 
-    let primary_key_iden_name = &primary_key.iden_name;
+    let primary_key_iden_names = primary_keys.iter().map(|f| &f.iden_name).collect::<Vec<_>>();
+
+    // a composite key has no single column to name
+    let primary_key_constant = match primary_key_iden_names.as_slice() {
+        [primary_key_iden_name] => quote! {
+            #vis const PRIMARY_KEY: #entity_enum_ident = #entity_enum_ident::#primary_key_iden_name;
+        },
+        _ => quote! {},
+    };
 
     Ok(quote! {
         #vis const COLUMNS: &'static [#entity_enum_ident] = &[#(#entity_enum_ident::#col_enum_variants),*];
-        #vis const PRIMARY_KEY: #entity_enum_ident = #entity_enum_ident::#primary_key_iden_name;
+        #vis const PRIMARY_KEYS: &'static [#entity_enum_ident] = &[#(#entity_enum_ident::#primary_key_iden_names),*];
+        #primary_key_constant
         #(#col_constants)*
     })
 }
@@ -254,38 +330,52 @@ fn generate_relation_functions(
             match relation {
                 Relation::ForeignKey {
                     entity: inversed_entity,
-                    foreign_key_field,
-                    references_field,
+                    foreign_key_fields,
+                    references_fields,
                 } => {
                     let field_name = &f.field_name;
-                    let references_field = references_field
-                        .as_ref();
 
-                    let inversed_primary_key_getter = match references_field {
+                    let foreign_key_columns = foreign_key_fields
+                        .iter()
+                        .map(|fk| {
+                            let iden = Ident::new(&to_upper_camel_case(&fk.to_string()), fk.span());
+                            quote! { #entity_name::#iden }
+                        })
+                        .collect::<Vec<_>>();
+
+                    // the referenced columns, paired up with the foreign key fields in order
+                    let (references_columns, inversed_filter) = match references_fields {
                         Some(refs) => {
-                            quote! { entity.#refs }
-                        },
+                            let columns = refs
+                                .iter()
+                                .map(|r| {
+                                    let iden = Ident::new(&to_upper_camel_case(&r.to_string()), r.span());
+                                    quote! { #inversed_entity::#iden }
+                                })
+                                .collect::<Vec<_>>();
+
+                            let comparisons = foreign_key_columns.iter().zip(refs).map(|(fk, r)| {
+                                quote! { kali::column::ColumnExpr::eq(#fk, entity.#r) }
+                            });
+
+                            (columns, and_all(comparisons))
+                        }
                         None => {
-                            quote! { entity.__primary_key_value() }
-                        },
-                    };
-
-                    let references_field_iden_ident = if let Some(refs) = references_field {
-                        Ident::new(
-                            &to_upper_camel_case(&refs.to_string()),
-                            refs.span(),
-                        )
-                    } else {
-                        Ident::new(
-                            "PRIMARY_KEY",
-                            foreign_key_field.span(),
-                        )
+                            let columns = (0..foreign_key_fields.len())
+                                .map(|i| quote! { #inversed_entity::PRIMARY_KEYS[#i] })
+                                .collect::<Vec<_>>();
+
+                            let filter = key_filter(&foreign_key_columns, quote! { key }, foreign_key_fields.len());
+                            (columns, quote! {
+                                let key = entity.__primary_key_value();
+                                #filter
+                            })
+                        }
                     };
 
-                    let foreign_key_iden_ident = Ident::new(
-                        &to_upper_camel_case(&foreign_key_field.to_string()),
-                        foreign_key_field.span(),
-                    );
+                    let forward_filter = and_all(references_columns.iter().zip(foreign_key_fields).map(|(r, fk)| {
+                        quote! { kali::column::ColumnExpr::eq(#r, self.#fk) }
+                    }));
 
                     let inversed_filter_name = Ident::new(
                         &format!("__{}_inversed_filter", field_name),
@@ -295,7 +385,7 @@ fn generate_relation_functions(
 
                     quote! {
                         #vis fn #field_name(&self) -> kali::reference::Reference<#inversed_entity> {
-                            kali::reference::Reference::new(#inversed_entity::#references_field_iden_ident.eq(self.#foreign_key_field))
+                            kali::reference::Reference::new(#forward_filter)
                         }
 
                         // this is really awkward, but its necessary for the inversed side to know
@@ -303,7 +393,7 @@ fn generate_relation_functions(
                         // to figure it out, and other workarounds aren't as clean.
                         #[doc(hidden)]
                         #vis fn #inversed_filter_name<'a>(entity: &#inversed_entity) -> kali::builder::expr::Expr<'a, #col_enum_name> {
-                            #entity_name::#foreign_key_iden_ident.eq(#inversed_primary_key_getter)
+                            #inversed_filter
                         }
                     }
                 }
@@ -347,17 +437,207 @@ fn generate_relation_functions(
     })
 }
 
+fn generate_insert_struct(
+    vis: &syn::Visibility,
+    entity_name: &Ident,
+    col_enum_name: &Ident,
+    parsed_fields: &[ParsedField],
+) -> syn::Result<TokenStream> {
+    let insert_name = Ident::new(&format!("New{}", entity_name), entity_name.span());
+
+    let insert_fields = parsed_fields
+        .iter()
+        .filter(|f| !f.is_auto_increment && !f.is_defaulted)
+        .collect::<Vec<_>>();
+
+    // the entity's attributes are for sqlx and the like, only its docs carry over
+    let field_definitions = insert_fields
+        .iter()
+        .map(|f| {
+            let mut field = f.raw.clone();
+            field.attrs.retain(|attr| attr.path().is_ident("doc"));
+            field
+        })
+        .collect::<Vec<_>>();
+
+    let values = insert_fields
+        .iter()
+        .map(|f| {
+            let field_name = &f.field_name;
+            let iden_name = &f.iden_name;
+            quote! { .value(#col_enum_name::#iden_name, self.#field_name) }
+        })
+        .collect::<Vec<_>>();
+
+    // with nothing left to set, every column takes its default
+    let default_values = insert_fields
+        .is_empty()
+        .then(|| quote! { .default_values() });
+
+    let doc = format!(
+        "The columns of [`{entity_name}`] to insert, without the ones the database fills in."
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #insert_name {
+            #(#field_definitions),*
+        }
+
+        impl #insert_name {
+            #vis async fn insert<'e, E>(self, executor: E) -> Result<#entity_name, kali::error::Error>
+            where
+                E: 'e + sqlx::Executor<'e>,
+                E::Database: kali::dialect::Backend,
+                #entity_name: for<'r> sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
+            {
+                kali::builder::QueryBuilder::insert_into(#entity_name::TABLE_NAME)
+                    #(#values)*
+                    #default_values
+                    .returning(#entity_name::COLUMNS)
+                    .fetch_one(executor)
+                    .await
+            }
+        }
+    })
+}
+
+fn generate_active_struct(
+    vis: &syn::Visibility,
+    entity_name: &Ident,
+    col_enum_name: &Ident,
+    parsed_fields: &[ParsedField],
+) -> syn::Result<TokenStream> {
+    let active_name = Ident::new(&format!("Active{}", entity_name), entity_name.span());
+
+    // changing the primary key would save over a different row, so it has no setter
+    let settable_fields = parsed_fields
+        .iter()
+        .filter(|f| !f.is_pk)
+        .collect::<Vec<_>>();
+
+    let setters = settable_fields
+        .iter()
+        .map(|f| {
+            let field_name = &f.field_name;
+            let iden_name = &f.iden_name;
+            let ty = &f.raw.ty;
+            let setter_name = Ident::new(&format!("set_{}", field_name), field_name.span());
+            quote! {
+                #vis fn #setter_name(&mut self, value: #ty) -> &mut Self {
+                    self.entity.#field_name = value;
+                    self.mark_changed(#col_enum_name::#iden_name);
+                    self
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let set_arms = settable_fields
+        .iter()
+        .map(|f| {
+            let field_name = &f.field_name;
+            let iden_name = &f.iden_name;
+            quote! {
+                #col_enum_name::#iden_name => query.set(*column, self.entity.#field_name.clone())
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let doc = format!(
+        "A [`{entity_name}`] that records which fields are set, so `save` only updates those columns."
+    );
+
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #active_name {
+            entity: #entity_name,
+            changed: Vec<#col_enum_name>,
+        }
+
+        impl #active_name {
+            #(#setters)*
+
+            /// The columns set since the entity was loaded or last saved.
+            #vis fn changed(&self) -> &[#col_enum_name] {
+                &self.changed
+            }
+
+            #vis fn into_inner(self) -> #entity_name {
+                self.entity
+            }
+
+            fn mark_changed(&mut self, column: #col_enum_name) {
+                if !self.changed.contains(&column) {
+                    self.changed.push(column);
+                }
+            }
+
+            /// Update the changed columns of the entity's row, or do nothing if none changed.
+            #vis async fn save<'e, E>(
+                &mut self,
+                executor: E,
+            ) -> Result<<E::Database as sqlx::Database>::QueryResult, kali::error::Error>
+            where
+                E: 'e + sqlx::Executor<'e>,
+                E::Database: kali::dialect::Backend,
+            {
+                if self.changed.is_empty() {
+                    return Ok(Default::default());
+                }
+
+                let mut query = kali::builder::QueryBuilder::update(#entity_name::TABLE_NAME);
+                for column in &self.changed {
+                    query = match column {
+                        #(#set_arms,)*
+                        _ => query,
+                    };
+                }
+
+                let result = query
+                    .filter(#entity_name::__primary_key_filter(
+                        self.entity.__primary_key_value(),
+                    ))
+                    .execute(executor)
+                    .await?;
+
+                self.changed.clear();
+                Ok(result)
+            }
+        }
+
+        impl std::ops::Deref for #active_name {
+            type Target = #entity_name;
+
+            fn deref(&self) -> &#entity_name {
+                &self.entity
+            }
+        }
+
+        impl #entity_name {
+            /// Track changes to the entity so they can be saved.
+            #vis fn into_active(self) -> #active_name {
+                #active_name {
+                    entity: self,
+                    changed: Vec::new(),
+                }
+            }
+        }
+    })
+}
+
 fn generate(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
     let mut entity: syn::ItemStruct = syn::parse2(input)?; // Make entity mutable
+    let args: EntityArgs = syn::parse2(args)?;
 
     // table name is either #[entity("table_name")] or snake_case of the struct name
-    let table_name = if args.is_empty() {
-        let name = entity.ident.to_string();
-        let snake_case_name = to_snake_case(&name);
-        quote! { #snake_case_name }
-    } else {
-        let table_name: syn::LitStr = syn::parse2(args)?;
-        quote! { #table_name }
+    let table_name = match args.table_name {
+        Some(table_name) => quote! { #table_name },
+        None => {
+            let name = entity.ident.to_string();
+            let snake_case_name = to_snake_case(&name);
+            quote! { #snake_case_name }
+        }
     };
 
     let entity_vis = entity.vis.clone();
@@ -380,26 +660,59 @@ fn generate(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
         }
     }
 
-    // pk is either with #[primary_key] attribute or named "id"
-    let primary_key = parsed_fields
-        .iter()
-        .find(|f| f.is_pk)
-        .or_else(|| parsed_fields.iter().find(|f| f.field_name == "id"));
-
-    let Some(primary_key) = primary_key else {
-        return Err(syn::Error::new_spanned(
-            &entity,
-            "missing primary key field with #[primary_key] attribute or named 'id'",
-        ));
+    // pk is either the fields with #[primary_key], several for a composite key, or the one named "id"
+    let mut parsed_fields = parsed_fields;
+    if !parsed_fields.iter().any(|f| f.is_pk) {
+        let Some(id) = parsed_fields.iter_mut().find(|f| f.field_name == "id") else {
+            return Err(syn::Error::new_spanned(
+                &entity,
+                "missing primary key field with #[primary_key] attribute or named 'id'",
+            ));
+        };
+
+        id.is_pk = true;
+    }
+
+    let primary_keys = parsed_fields.iter().filter(|f| f.is_pk).collect::<Vec<_>>();
+    let primary_key_names = primary_keys.iter().map(|f| &f.field_name).collect::<Vec<_>>();
+    let primary_key_iden_names = primary_keys.iter().map(|f| &f.iden_name).collect::<Vec<_>>();
+    let primary_key_types = primary_keys.iter().map(|f| &f.raw.ty).collect::<Vec<_>>();
+
+    // a single key is passed as is, a composite one as a tuple in field order
+    let (primary_key_type, primary_key_value) = match primary_keys.as_slice() {
+        [primary_key] => {
+            let name = &primary_key.field_name;
+            let ty = &primary_key.raw.ty;
+            (quote! { #ty }, quote! { self.#name })
+        }
+        _ => (
+            quote! { (#(#primary_key_types),*) },
+            quote! { (#(self.#primary_key_names),*) },
+        ),
     };
 
-    let primary_key_name = &primary_key.field_name;
-    let primary_key_type = &primary_key.raw.ty;
     let (col_enum_name, col_enum) =
-        generate_entity_column_enum(&entity_vis, &entity_name, &parsed_fields)?;
+        generate_entity_column_enum(&entity_vis, &entity_name, &table_name, &parsed_fields)?;
     let entity_constants =
-        generate_entity_constants(&entity_vis, &col_enum_name, &parsed_fields, primary_key)?;
+        generate_entity_constants(&entity_vis, &col_enum_name, &parsed_fields, &primary_keys)?;
 
+    let key_columns = primary_key_iden_names
+        .iter()
+        .map(|iden_name| quote! { #col_enum_name::#iden_name })
+        .collect::<Vec<_>>();
+    let primary_key_filter = key_filter(&key_columns, quote! { id }, primary_keys.len());
+
+    // opt in, as they only compile when the field types convert into a Value
+    let insert_struct = if args.insertable {
+        generate_insert_struct(&entity_vis, &entity_name, &col_enum_name, &parsed_fields)?
+    } else {
+        quote! {}
+    };
+    let active_struct = if args.active {
+        generate_active_struct(&entity_vis, &entity_name, &col_enum_name, &parsed_fields)?
+    } else {
+        quote! {}
+    };
     let relation_functions = generate_relation_functions(
         &entity_name,
         &col_enum_name,
@@ -420,13 +733,15 @@ fn generate(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
             #entity_vis async fn fetch_one<'e, E>(
                 executor: E,
                 id: #primary_key_type,
-            ) -> Result<Self, sqlx::Error>
+            ) -> Result<Self, kali::error::Error>
             where
-                E: 'e + sqlx::Executor<'e, Database = sqlx::Sqlite>,
+                E: 'e + sqlx::Executor<'e>,
+                E::Database: kali::dialect::Backend,
+                Self: for<'r> sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
             {
                 kali::builder::QueryBuilder::select_from(Self::TABLE_NAME)
                     .columns(Self::COLUMNS)
-                    .filter(Self::PRIMARY_KEY.eq(id))
+                    .filter(Self::__primary_key_filter(id))
                     .limit(1)
                     .fetch_one(executor)
                     .await
@@ -435,13 +750,15 @@ fn generate(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
             #entity_vis async fn fetch_optional<'e, E>(
                 executor: E,
                 id: #primary_key_type,
-            ) -> Result<Option<Self>, sqlx::Error>
+            ) -> Result<Option<Self>, kali::error::Error>
             where
-                E: 'e + sqlx::Executor<'e, Database = sqlx::Sqlite>,
+                E: 'e + sqlx::Executor<'e>,
+                E::Database: kali::dialect::Backend,
+                Self: for<'r> sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
             {
                 kali::builder::QueryBuilder::select_from(Self::TABLE_NAME)
                     .columns(Self::COLUMNS)
-                    .filter(Self::PRIMARY_KEY.eq(id))
+                    .filter(Self::__primary_key_filter(id))
                     .limit(1)
                     .fetch_optional(executor)
                     .await
@@ -449,9 +766,11 @@ fn generate(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
 
             #entity_vis async fn fetch_all<'e, E>(
                 executor: E,
-            ) -> Result<Vec<Self>, sqlx::Error>
+            ) -> Result<Vec<Self>, kali::error::Error>
             where
-                E: 'e + sqlx::Executor<'e, Database = sqlx::Sqlite>,
+                E: 'e + sqlx::Executor<'e>,
+                E::Database: kali::dialect::Backend,
+                Self: for<'r> sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
             {
                 kali::builder::QueryBuilder::select_from(Self::TABLE_NAME)
                     .columns(Self::COLUMNS)
@@ -459,22 +778,47 @@ fn generate(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
                     .await
             }
 
+            #entity_vis async fn fetch_page<'c, A, DB>(
+                conn: A,
+                page: i64,
+                per_page: i64,
+            ) -> Result<kali::builder::pagination::Page<Self>, kali::error::Error>
+            where
+                A: sqlx::Acquire<'c, Database = DB>,
+                DB: kali::dialect::Backend,
+                for<'x> &'x mut DB::Connection: sqlx::Executor<'x, Database = DB>,
+                Self: for<'r> sqlx::FromRow<'r, DB::Row>,
+                (i64,): for<'r> sqlx::FromRow<'r, DB::Row>,
+            {
+                kali::builder::QueryBuilder::select_from(Self::TABLE_NAME)
+                    .columns(Self::COLUMNS)
+                    #(.order_by(kali::column::ColumnExpr::asc(#key_columns)))*
+                    .fetch_page(conn, page, per_page)
+                    .await
+            }
+
             #entity_vis async fn delete_one<'e, E>(
                 executor: E,
                 id: #primary_key_type,
-            ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error>
+            ) -> Result<<E::Database as sqlx::Database>::QueryResult, kali::error::Error>
             where
-                E: 'e + sqlx::Executor<'e, Database = sqlx::Sqlite>,
+                E: 'e + sqlx::Executor<'e>,
+                E::Database: kali::dialect::Backend,
             {
                 kali::builder::QueryBuilder::delete_from(Self::TABLE_NAME)
-                    .filter(Self::PRIMARY_KEY.eq(id))
+                    .filter(Self::__primary_key_filter(id))
                     .execute(executor)
                     .await
             }
 
             #[doc(hidden)]
             #entity_vis fn __primary_key_value(&self) -> #primary_key_type {
-                self.#primary_key_name
+                #primary_key_value
+            }
+
+            #[doc(hidden)]
+            #entity_vis fn __primary_key_filter<'a>(id: #primary_key_type) -> kali::builder::expr::Expr<'a, #col_enum_name> {
+                #primary_key_filter
             }
         }
 
@@ -489,15 +833,57 @@ fn generate(args: TokenStream, input: TokenStream) -> syn::Result<TokenStream> {
                 Self::COLUMNS
             }
 
-            fn primary_key() -> &'static #col_enum_name {
-                &Self::PRIMARY_KEY
+            fn primary_key() -> &'static [#col_enum_name] {
+                Self::PRIMARY_KEYS
             }
         }
 
         #col_enum
+
+        #insert_struct
+
+        #active_struct
     })
 }
 
+/// Parse a field name, or a parenthesised list of them for a composite key.
+fn parse_field_list(input: syn::parse::ParseStream) -> syn::Result<Vec<Ident>> {
+    if input.peek(syn::token::Paren) {
+        let content;
+        syn::parenthesized!(content in input);
+        let fields = content.parse_terminated(<Ident as syn::parse::Parse>::parse, syn::Token![,])?;
+        if fields.is_empty() {
+            return Err(content.error("expected at least one field"));
+        }
+
+        Ok(fields.into_iter().collect())
+    } else {
+        Ok(vec![input.parse()?])
+    }
+}
+
+/// Match `columns` against `key`, which is a single value for one column or a tuple of as many
+/// values as there are columns.
+fn key_filter(columns: &[TokenStream], key: TokenStream, len: usize) -> TokenStream {
+    let comparisons = columns.iter().enumerate().map(|(i, column)| {
+        if len == 1 {
+            quote! { kali::column::ColumnExpr::eq(#column, #key) }
+        } else {
+            let index = syn::Index::from(i);
+            quote! { kali::column::ColumnExpr::eq(#column, #key.#index) }
+        }
+    });
+
+    and_all(comparisons)
+}
+
+/// Combine expressions with `AND`.
+fn and_all(exprs: impl Iterator<Item = TokenStream>) -> TokenStream {
+    exprs
+        .reduce(|left, right| quote! { kali::builder::expr::Expr::and(#left, #right) })
+        .expect("at least one expression")
+}
+
 fn to_snake_case(name: &str) -> String {
     let mut result = String::new();
     let mut prev_was_upper = false;
@@ -0,0 +1,125 @@
+use super::writer::SqlWriter;
+use crate::column::{Column, ColumnRef};
+
+/// An aggregate function over a column, usable in the select list and in `HAVING`.
+///
+/// Aggregates implement [`Column`] so they can be compared and ordered with the same
+/// [`ColumnExpr`](crate::column::ColumnExpr) methods as plain columns, eg `Post::Id.count().gt(2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aggregate<C: Column> {
+    CountAll,
+    Count(C),
+    CountDistinct(C),
+    Sum(C),
+    Avg(C),
+    Min(C),
+    Max(C),
+}
+
+impl<C: Column> Aggregate<C> {
+    pub fn count_all() -> Self {
+        Aggregate::CountAll
+    }
+
+    fn function(&self) -> AggregateFunction {
+        match self {
+            Aggregate::CountAll => AggregateFunction::CountAll,
+            Aggregate::Count(_) => AggregateFunction::Count,
+            Aggregate::CountDistinct(_) => AggregateFunction::CountDistinct,
+            Aggregate::Sum(_) => AggregateFunction::Sum,
+            Aggregate::Avg(_) => AggregateFunction::Avg,
+            Aggregate::Min(_) => AggregateFunction::Min,
+            Aggregate::Max(_) => AggregateFunction::Max,
+        }
+    }
+
+    fn column(&self) -> Option<&C> {
+        match self {
+            Aggregate::CountAll => None,
+            Aggregate::Count(column)
+            | Aggregate::CountDistinct(column)
+            | Aggregate::Sum(column)
+            | Aggregate::Avg(column)
+            | Aggregate::Min(column)
+            | Aggregate::Max(column) => Some(column),
+        }
+    }
+
+    fn write_with(&self, w: &mut SqlWriter, write_column: impl FnOnce(&C, &mut SqlWriter)) {
+        self.function().write_with(w, |w| {
+            if let Some(column) = self.column() {
+                write_column(column, w);
+            }
+        });
+    }
+}
+
+/// The function of an [`Aggregate`], kept on a [`ColumnRef`] when the aggregate is erased.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunction {
+    CountAll,
+    Count,
+    CountDistinct,
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateFunction {
+    fn name(&self) -> &'static str {
+        match self {
+            AggregateFunction::CountAll
+            | AggregateFunction::Count
+            | AggregateFunction::CountDistinct => "COUNT",
+            AggregateFunction::Sum => "SUM",
+            AggregateFunction::Avg => "AVG",
+            AggregateFunction::Min => "MIN",
+            AggregateFunction::Max => "MAX",
+        }
+    }
+
+    pub(crate) fn write_with(&self, w: &mut SqlWriter, write_column: impl FnOnce(&mut SqlWriter)) {
+        w.push_str(self.name());
+        w.push('(');
+        match self {
+            AggregateFunction::CountAll => w.push('*'),
+            AggregateFunction::CountDistinct => {
+                w.push_str("DISTINCT ");
+                write_column(w);
+            }
+            _ => write_column(w),
+        }
+
+        w.push(')');
+    }
+}
+
+impl<C: Column> Column for Aggregate<C> {
+    fn to_col_name(&self) -> &'static str {
+        match self.column() {
+            Some(column) => column.to_col_name(),
+            None => "*",
+        }
+    }
+
+    fn table_name(&self) -> Option<&'static str> {
+        self.column().and_then(|column| column.table_name())
+    }
+
+    fn write(&self, w: &mut SqlWriter) {
+        self.write_with(w, |column, w| column.write(w));
+    }
+
+    fn write_qualified(&self, w: &mut SqlWriter) {
+        self.write_with(w, |column, w| column.write_qualified(w));
+    }
+
+    fn to_ref(&self) -> ColumnRef {
+        ColumnRef {
+            table: self.table_name(),
+            name: self.to_col_name(),
+            aggregate: Some(self.function()),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use super::writer::SqlWriter;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompoundOperator {
+    Union,
+    UnionAll,
+    Intersect,
+    Except,
+}
+
+impl CompoundOperator {
+    pub(crate) fn write(&self, w: &mut SqlWriter) {
+        match self {
+            CompoundOperator::Union => w.push_str(" UNION "),
+            CompoundOperator::UnionAll => w.push_str(" UNION ALL "),
+            CompoundOperator::Intersect => w.push_str(" INTERSECT "),
+            CompoundOperator::Except => w.push_str(" EXCEPT "),
+        }
+    }
+}
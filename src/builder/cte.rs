@@ -0,0 +1,38 @@
+use super::expr::Subquery;
+use super::writer::SqlWriter;
+use crate::error::BuildError;
+
+/// A common table expression. Its body can select from any entity.
+#[derive(Clone)]
+pub struct Cte<'a> {
+    name: &'a str,
+    base: Subquery<'a>,
+    // the self-referencing half of a recursive expression, joined to `base` with UNION ALL
+    recursive: Option<Subquery<'a>>,
+}
+
+impl<'a> Cte<'a> {
+    pub(crate) fn new(name: &'a str, base: Subquery<'a>, recursive: Option<Subquery<'a>>) -> Self {
+        Self {
+            name,
+            base,
+            recursive,
+        }
+    }
+
+    pub fn is_recursive(&self) -> bool {
+        self.recursive.is_some()
+    }
+
+    pub(crate) fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        w.push_identifier(self.name);
+        w.push_str(" AS (");
+        self.base.write(w)?;
+        if let Some(recursive) = self.recursive {
+            w.push_str(" UNION ALL ");
+            recursive.write(w)?;
+        }
+        w.push(')');
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+/// The plan SQLite picked for a query, as reported by `EXPLAIN QUERY PLAN`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPlan {
+    pub nodes: Vec<PlanNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanNode {
+    pub id: i64,
+    /// eg `SEARCH users USING INTEGER PRIMARY KEY (rowid=?)` or `SCAN posts`
+    pub detail: String,
+    pub children: Vec<PlanNode>,
+}
+
+impl QueryPlan {
+    /// Build the tree from `(id, parent, notused, detail)` rows, where top level nodes have a parent of 0.
+    pub(crate) fn from_rows(rows: &[(i64, i64, i64, String)]) -> Self {
+        Self {
+            nodes: PlanNode::children_of(rows, 0),
+        }
+    }
+
+    /// Every node in the plan, depth first.
+    pub fn iter(&self) -> impl Iterator<Item = &PlanNode> {
+        let mut stack: Vec<&PlanNode> = self.nodes.iter().rev().collect();
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            stack.extend(node.children.iter().rev());
+            Some(node)
+        })
+    }
+
+    /// Nodes that read every row of a table, directly or through one of its indexes.
+    pub fn full_scans(&self) -> Vec<&PlanNode> {
+        self.iter()
+            .filter(|node| node.scanned_table().is_some())
+            .collect()
+    }
+}
+
+impl PlanNode {
+    fn children_of(rows: &[(i64, i64, i64, String)], parent: i64) -> Vec<PlanNode> {
+        rows.iter()
+            .filter(|(_, row_parent, _, _)| *row_parent == parent)
+            .map(|(id, _, _, detail)| PlanNode {
+                id: *id,
+                detail: detail.clone(),
+                children: Self::children_of(rows, *id),
+            })
+            .collect()
+    }
+
+    /// The table read by a `SCAN` node, if this is one. Scans that walk an index, eg
+    /// `SCAN users USING COVERING INDEX ...`, count too as they still visit every row.
+    pub fn scanned_table(&self) -> Option<&str> {
+        let rest = self.detail.strip_prefix("SCAN ")?;
+        // versions before 3.36 wrote "SCAN TABLE users"
+        let rest = rest.strip_prefix("TABLE ").unwrap_or(rest);
+        let table = rest.split(' ').next()?;
+
+        // subqueries and constant rows are scanned by design
+        if table.starts_with('(') || rest.starts_with("CONSTANT ROW") {
+            return None;
+        }
+
+        Some(table)
+    }
+}
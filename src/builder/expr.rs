@@ -1,15 +1,72 @@
+use super::fragment::Fragment;
 use super::value::Value;
-use crate::column::Column;
+use super::value_expr::ValueExpr;
+use super::writer::SqlWriter;
+use super::{QueryBuilder, Select};
+use crate::column::{Column, ColumnRef};
+use crate::error::BuildError;
 use std::borrow::Cow;
+use std::sync::Arc;
+
+/// A select query nested inside another query, regardless of which entity it selects from.
+#[derive(Clone)]
+pub struct Subquery<'a> {
+    query: Arc<dyn ErasedSelect + 'a>,
+}
+
+impl<'a> Subquery<'a> {
+    pub fn new<D: Column + 'a>(query: QueryBuilder<'a, Select, D>) -> Self {
+        Self {
+            query: Arc::new(query),
+        }
+    }
+
+    /// Write the query without surrounding parentheses.
+    pub(crate) fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        self.query.write(w)
+    }
+}
+
+// subqueries are rendered along with the outer query so placeholders are numbered in order
+trait ErasedSelect: Send + Sync {
+    fn write(&self, w: &mut SqlWriter) -> Result<(), BuildError>;
+}
+
+impl<D: Column> ErasedSelect for QueryBuilder<'_, Select, D> {
+    fn write(&self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        let qualify = !self.joins.is_empty();
+        self.clone().write_sql(w, qualify)
+    }
+}
 
 #[derive(Clone)]
 pub enum Expr<'a, C: Column> {
     Equal(C, Value),
+    NotEqual(C, Value),
     Gt(C, Value),
+    Gte(C, Value),
     Lt(C, Value),
+    Lte(C, Value),
+    Between(C, Value, Value),
     Like(C, Value),
+    NotLike(C, Value),
+    Glob(C, Value),
+    /// A null-safe `!=`, true when exactly one side is `NULL`.
+    IsDistinctFrom(C, Value),
     In(C, Vec<Value>),
+    NotIn(C, Vec<Value>),
+    InSubquery(C, Subquery<'a>),
+    Exists(Subquery<'a>),
+    NotExists(Subquery<'a>),
+    /// A column compared to another column, which may belong to a different entity.
+    ColumnCompare(C, &'static str, ColumnRef),
+    Compare(ValueExpr<'a>, &'static str, ValueExpr<'a>),
     Raw(Cow<'a, str>),
+    Not(Box<Expr<'a, C>>),
+    /// Raw SQL with bound values, from [`sql!`](crate::sql).
+    Fragment(Fragment<'a>),
+    /// An expression over another entity's columns, for filtering on joined tables.
+    Erased(Box<Expr<'a, ColumnRef>>),
     And(Box<Expr<'a, C>>, Box<Expr<'a, C>>),
     Or(Box<Expr<'a, C>>, Box<Expr<'a, C>>),
 }
@@ -31,65 +88,237 @@ impl<'a, C: Column> Expr<'a, C> {
         Expr::Or(Box::new(left), Box::new(right))
     }
 
-    pub(crate) fn write(self, f: &mut String, values: &mut Vec<Value>) {
+    pub fn exists<D: Column + 'a>(query: QueryBuilder<'a, Select, D>) -> Expr<'a, C> {
+        Expr::Exists(Subquery::new(query))
+    }
+
+    pub fn not_exists<D: Column + 'a>(query: QueryBuilder<'a, Select, D>) -> Expr<'a, C> {
+        Expr::NotExists(Subquery::new(query))
+    }
+
+    /// Erase the column type so the expression can be used in a query over a different entity.
+    pub fn erase(self) -> Expr<'a, ColumnRef> {
+        match self {
+            Expr::Equal(column, value) => Expr::Equal(column.to_ref(), value),
+            Expr::NotEqual(column, value) => Expr::NotEqual(column.to_ref(), value),
+            Expr::Gt(column, value) => Expr::Gt(column.to_ref(), value),
+            Expr::Gte(column, value) => Expr::Gte(column.to_ref(), value),
+            Expr::Lt(column, value) => Expr::Lt(column.to_ref(), value),
+            Expr::Lte(column, value) => Expr::Lte(column.to_ref(), value),
+            Expr::Between(column, low, high) => Expr::Between(column.to_ref(), low, high),
+            Expr::Like(column, value) => Expr::Like(column.to_ref(), value),
+            Expr::NotLike(column, value) => Expr::NotLike(column.to_ref(), value),
+            Expr::Glob(column, value) => Expr::Glob(column.to_ref(), value),
+            Expr::IsDistinctFrom(column, value) => Expr::IsDistinctFrom(column.to_ref(), value),
+            Expr::In(column, values) => Expr::In(column.to_ref(), values),
+            Expr::NotIn(column, values) => Expr::NotIn(column.to_ref(), values),
+            Expr::InSubquery(column, subquery) => Expr::InSubquery(column.to_ref(), subquery),
+            Expr::Exists(subquery) => Expr::Exists(subquery),
+            Expr::NotExists(subquery) => Expr::NotExists(subquery),
+            Expr::ColumnCompare(column, operator, other) => {
+                Expr::ColumnCompare(column.to_ref(), operator, other)
+            }
+            Expr::Compare(left, operator, right) => Expr::Compare(left, operator, right),
+            Expr::Raw(raw) => Expr::Raw(raw),
+            Expr::Fragment(fragment) => Expr::Fragment(fragment),
+            Expr::Not(expr) => Expr::Not(Box::new(expr.erase())),
+            Expr::Erased(expr) => *expr,
+            Expr::And(left, right) => Expr::And(Box::new(left.erase()), Box::new(right.erase())),
+            Expr::Or(left, right) => Expr::Or(Box::new(left.erase()), Box::new(right.erase())),
+        }
+    }
+
+    pub(crate) fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
         match self {
             Expr::Equal(column, value) => {
-                column.write(f);
+                w.push_column(&column);
+                match value {
+                    Value::Null => w.push_str(" IS NULL"),
+                    _ => {
+                        w.push_str(" = ");
+                        w.push_value(value)?;
+                    }
+                }
+            }
+            Expr::NotEqual(column, value) => {
+                w.push_column(&column);
                 match value {
-                    Value::Null => f.push_str(" IS NULL"),
+                    Value::Null => w.push_str(" IS NOT NULL"),
                     _ => {
-                        f.push_str(" = ?");
-                        values.push(value);
+                        w.push_str(" != ");
+                        w.push_value(value)?;
                     }
                 }
             }
             Expr::Gt(column, value) => {
-                column.write(f);
-                f.push_str(" > ?");
-                values.push(value);
+                w.push_column(&column);
+                w.push_str(" > ");
+                w.push_value(value)?;
+            }
+            Expr::Gte(column, value) => {
+                w.push_column(&column);
+                w.push_str(" >= ");
+                w.push_value(value)?;
             }
             Expr::Lt(column, value) => {
-                column.write(f);
-                f.push_str(" < ?");
-                values.push(value);
+                w.push_column(&column);
+                w.push_str(" < ");
+                w.push_value(value)?;
+            }
+            Expr::Lte(column, value) => {
+                w.push_column(&column);
+                w.push_str(" <= ");
+                w.push_value(value)?;
+            }
+            Expr::Between(column, low, high) => {
+                w.push_column(&column);
+                w.push_str(" BETWEEN ");
+                w.push_value(low)?;
+                w.push_str(" AND ");
+                w.push_value(high)?;
+            }
+            Expr::IsDistinctFrom(column, value) => match w.dialect().distinct_from_operator() {
+                Some(operator) => {
+                    w.push_column(&column);
+                    w.push_str(operator);
+                    w.push_value(value)?;
+                }
+                None => {
+                    w.push_str("NOT (");
+                    w.push_column(&column);
+                    w.push_str(" <=> ");
+                    w.push_value(value)?;
+                    w.push(')');
+                }
+            },
+            Expr::Not(expr) => {
+                w.push_str("NOT (");
+                expr.write(w)?;
+                w.push(')');
             }
             Expr::And(left, right) => {
-                f.push_str("(");
-                left.write(f, values);
-                f.push_str(") AND (");
-                right.write(f, values);
-                f.push_str(")");
+                w.push('(');
+                left.write(w)?;
+                w.push_str(") AND (");
+                right.write(w)?;
+                w.push(')');
             }
             Expr::Or(left, right) => {
-                f.push_str("(");
-                left.write(f, values);
-                f.push_str(") OR (");
-                right.write(f, values);
-                f.push_str(")");
+                w.push('(');
+                left.write(w)?;
+                w.push_str(") OR (");
+                right.write(w)?;
+                w.push(')');
             }
             Expr::Raw(raw) => {
-                f.push_str(&raw);
+                w.push_str(&raw);
+            }
+            Expr::Fragment(fragment) => {
+                fragment.write(w)?;
+            }
+            Expr::Erased(expr) => {
+                expr.write(w)?;
             }
             Expr::Like(column, value) => {
-                column.write(f);
-                f.push_str(" LIKE ?");
-                values.push(value);
+                w.push_column(&column);
+                w.push_str(" LIKE ");
+                w.push_value(value)?;
             }
-            Expr::In(column, values_list) => {
-                column.write(f);
-                f.push_str(" IN (");
-                let mut first = true;
-                for value in values_list {
-                    if !first {
-                        f.push_str(", ");
-                    } else {
-                        first = false;
-                    }
-                    f.push('?');
-                    values.push(value);
+            Expr::NotLike(column, value) => {
+                w.push_column(&column);
+                w.push_str(" NOT LIKE ");
+                w.push_value(value)?;
+            }
+            Expr::Glob(column, value) => {
+                if !w.dialect().supports_glob() {
+                    return Err(BuildError::Unsupported("GLOB"));
+                }
+
+                w.push_column(&column);
+                w.push_str(" GLOB ");
+                w.push_value(value)?;
+            }
+            Expr::ColumnCompare(column, operator, other) => {
+                // columns of two different tables are ambiguous unqualified, even without a join
+                let outer_qualify = w.qualify;
+                if column.table_name().is_some() && column.table_name() != other.table {
+                    w.qualify = true;
                 }
-                f.push(')');
+
+                w.push_column(&column);
+                w.push_str(operator);
+                w.push_column(&other);
+                w.qualify = outer_qualify;
+            }
+            Expr::Compare(left, operator, right) => {
+                left.write(w)?;
+                w.push_str(operator);
+                right.write(w)?;
+            }
+            Expr::In(column, values_list) => {
+                w.push_column(&column);
+                w.push_str(" IN ");
+                write_value_list(w, values_list)?;
+            }
+            Expr::NotIn(column, values_list) => {
+                w.push_column(&column);
+                w.push_str(" NOT IN ");
+                write_value_list(w, values_list)?;
+            }
+            Expr::InSubquery(column, subquery) => {
+                w.push_column(&column);
+                w.push_str(" IN ");
+                write_subquery(w, subquery)?;
             }
+            Expr::Exists(subquery) => {
+                w.push_str("EXISTS ");
+                write_subquery(w, subquery)?;
+            }
+            Expr::NotExists(subquery) => {
+                w.push_str("NOT EXISTS ");
+                write_subquery(w, subquery)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn write_value_list(w: &mut SqlWriter, values: Vec<Value>) -> Result<(), BuildError> {
+    w.push('(');
+    let mut first = true;
+    for value in values {
+        if !first {
+            w.push_str(", ");
+        } else {
+            first = false;
         }
+        w.push_value(value)?;
+    }
+    w.push(')');
+    Ok(())
+}
+
+pub(crate) fn write_subquery(w: &mut SqlWriter, subquery: Subquery) -> Result<(), BuildError> {
+    // unqualified columns resolve against the subquery's own table first, which may be a CTE or
+    // another name for the entity's table. references to the outer query are qualified by
+    // `ColumnCompare` since they belong to a different table.
+    w.push('(');
+    subquery.write(w)?;
+    w.push(')');
+    Ok(())
+}
+
+impl<'a, C: Column> std::ops::Not for Expr<'a, C> {
+    type Output = Expr<'a, C>;
+
+    fn not(self) -> Expr<'a, C> {
+        Expr::Not(Box::new(self))
+    }
+}
+
+impl<'a, C: Column> From<Fragment<'a>> for Expr<'a, C> {
+    fn from(fragment: Fragment<'a>) -> Self {
+        Expr::Fragment(fragment)
     }
 }
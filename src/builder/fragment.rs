@@ -0,0 +1,132 @@
+use super::value::Value;
+use super::writer::SqlWriter;
+use crate::column::{Column, ColumnRef};
+use crate::error::BuildError;
+use std::borrow::Cow;
+
+/// A piece of raw SQL that binds its values instead of splicing them into the text, built with
+/// [`sql!`](crate::sql).
+///
+/// In the template `{}` is replaced by the next argument, which must be a column, and `?` by a
+/// placeholder for the next argument, which must be a value. `{{`, `}}` and `??` write a literal
+/// `{`, `}` and `?`.
+#[derive(Clone)]
+pub struct Fragment<'a> {
+    template: Cow<'a, str>,
+    arguments: Vec<Argument>,
+}
+
+/// An argument interpolated into a [`Fragment`].
+#[derive(Clone, Debug)]
+pub enum Argument {
+    Column(ColumnRef),
+    Value(Value),
+}
+
+pub trait IntoArgument {
+    fn into_argument(self) -> Argument;
+}
+
+impl<C: Column> IntoArgument for C {
+    fn into_argument(self) -> Argument {
+        Argument::Column(self.to_ref())
+    }
+}
+
+macro_rules! value_argument {
+    ($($type:ty),*) => {
+        $(
+            impl IntoArgument for $type {
+                fn into_argument(self) -> Argument {
+                    Argument::Value(self.into())
+                }
+            }
+        )*
+    };
+}
+
+value_argument!(
+    Value,
+    bool,
+    String,
+    i64,
+    f64,
+    Vec<u8>,
+    i8,
+    i16,
+    i32,
+    u32,
+    f32,
+    u8,
+    u16,
+    &str,
+    &[u8],
+    ()
+);
+
+impl<T: Into<Value>> IntoArgument for Option<T> {
+    fn into_argument(self) -> Argument {
+        Argument::Value(self.into())
+    }
+}
+
+impl<'a> Fragment<'a> {
+    pub fn new<T: Into<Cow<'a, str>>>(template: T, arguments: Vec<Argument>) -> Self {
+        Self {
+            template: template.into(),
+            arguments,
+        }
+    }
+
+    pub(crate) fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        let mut arguments = self.arguments.into_iter();
+        let mut position = 0;
+        let mut chars = self.template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            let expected = match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) | ('?', Some('?')) => {
+                    chars.next();
+                    w.push(c);
+                    continue;
+                }
+                ('{', Some('}')) => {
+                    chars.next();
+                    "column"
+                }
+                ('?', _) => "value",
+                _ => {
+                    w.push(c);
+                    continue;
+                }
+            };
+
+            position += 1;
+            match (expected, arguments.next()) {
+                ("column", Some(Argument::Column(column))) => w.push_column(&column),
+                ("value", Some(Argument::Value(value))) => w.push_value(value)?,
+                _ => return Err(BuildError::FragmentArgument { position, expected }),
+            }
+        }
+
+        match arguments.len() {
+            0 => Ok(()),
+            extra => Err(BuildError::FragmentArgumentCount {
+                placeholders: position,
+                arguments: position + extra,
+            }),
+        }
+    }
+}
+
+/// Build a [`Fragment`] of raw SQL with columns and bound values interpolated into it,
+/// eg `sql!("{} > julianday('now') - ?", Post::CreatedAt, days)`.
+#[macro_export]
+macro_rules! sql {
+    ($template:expr $(, $argument:expr)* $(,)?) => {
+        $crate::builder::fragment::Fragment::new(
+            $template,
+            vec![$($crate::builder::fragment::IntoArgument::into_argument($argument)),*],
+        )
+    };
+}
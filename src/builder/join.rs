@@ -0,0 +1,41 @@
+use super::expr::Expr;
+use super::writer::SqlWriter;
+use crate::column::ColumnRef;
+use crate::error::BuildError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Cross,
+}
+
+#[derive(Clone)]
+pub struct Join<'a> {
+    kind: JoinKind,
+    table: &'a str,
+    on: Option<Expr<'a, ColumnRef>>,
+}
+
+impl<'a> Join<'a> {
+    pub fn new(kind: JoinKind, table: &'a str, on: Option<Expr<'a, ColumnRef>>) -> Self {
+        Self { kind, table, on }
+    }
+
+    pub(crate) fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        match self.kind {
+            JoinKind::Inner => w.push_str(" INNER JOIN "),
+            JoinKind::Left => w.push_str(" LEFT JOIN "),
+            JoinKind::Cross => w.push_str(" CROSS JOIN "),
+        }
+
+        w.push_str(self.table);
+
+        if let Some(on) = self.on {
+            w.push_str(" ON ");
+            on.write(w)?;
+        }
+
+        Ok(())
+    }
+}
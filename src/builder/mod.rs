@@ -1,19 +1,93 @@
 use super::{builder::expr::Expr, builder::ordering::ColumnOrdering};
 use crate::column::Column;
+use crate::dialect::{Backend, Dialect, Sqlite, UpsertStyle};
+use crate::error::{BuildError, Error};
+use crate::instrument::QueryTrace;
+use aggregate::Aggregate;
+use compound::CompoundOperator;
+use cte::Cte;
+use explain::QueryPlan;
+use expr::Subquery;
+use fragment::Fragment;
+use futures_core::Stream;
+use futures_util::TryStreamExt;
+use join::{Join, JoinKind};
+use ordering::OrderTerm;
+use pagination::{Cursor, CursorPage, Keyset, Page, keyset_columns, keyset_filter};
+use sqlx::{Database, FromRow};
 use std::marker::PhantomData;
+use tracing::Instrument;
 use value::Value;
+use value_expr::ValueExpr;
+use writer::SqlWriter;
 
+pub mod aggregate;
+pub mod compound;
+pub mod cte;
+pub mod explain;
 pub mod expr;
+pub mod fragment;
+pub mod join;
 pub mod ordering;
+pub mod pagination;
 pub mod value;
+pub mod value_expr;
+pub mod writer;
 
 pub struct Select;
 pub struct Insert;
 pub struct Update;
 pub struct Delete;
+/// An insert with a `DO UPDATE` conflict clause that has no `SET` values yet.
 pub struct OnConflicted;
+/// An insert with a `DO UPDATE SET` conflict clause.
+pub struct Upsert;
 
-#[derive(PartialEq, Eq)]
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::Select {}
+    impl Sealed for super::Insert {}
+    impl Sealed for super::Update {}
+    impl Sealed for super::Delete {}
+    impl Sealed for super::OnConflicted {}
+    impl Sealed for super::Upsert {}
+}
+
+/// Query states that are complete and can be sent to the database.
+pub trait Executable: sealed::Sealed {}
+impl Executable for Select {}
+impl Executable for Insert {}
+impl Executable for Update {}
+impl Executable for Delete {}
+impl Executable for Upsert {}
+
+/// Query states that accept a `WHERE` clause.
+///
+/// ```compile_fail
+/// # use kali::builder::QueryBuilder;
+/// # use kali::column::{ColumnExpr, ColumnRef};
+/// # const ID: ColumnRef = ColumnRef { table: None, name: "id", aggregate: None };
+/// QueryBuilder::insert_into("users").value(ID, 1).filter(ID.eq(1));
+/// ```
+pub trait Filterable: sealed::Sealed {}
+impl Filterable for Select {}
+impl Filterable for Update {}
+impl Filterable for Delete {}
+
+/// Query states that accept a `RETURNING` clause.
+pub trait Returnable: sealed::Sealed {}
+impl Returnable for Insert {}
+impl Returnable for Update {}
+impl Returnable for Delete {}
+impl Returnable for Upsert {}
+
+/// Query states that insert rows.
+pub trait Insertable: Executable {}
+impl Insertable for Insert {}
+impl Insertable for Upsert {}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum QueryKind {
     Select,
     Insert,
@@ -21,26 +95,28 @@ enum QueryKind {
     Delete,
 }
 
+impl QueryKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            QueryKind::Select => "SELECT",
+            QueryKind::Insert => "INSERT",
+            QueryKind::Update => "UPDATE",
+            QueryKind::Delete => "DELETE",
+        }
+    }
+}
+
 macro_rules! generic_builder {
     ($name:ident, $kind:expr) => {
         pub fn $name(table: &'a str) -> Self {
-            Self {
-                table,
-                kind: $kind,
-                columns: None,
-                filter: None,
-                limit: None,
-                offset: None,
-                values: None,
-                set: None,
-                on_conflict: None,
-                returning: None,
-                order_by: Vec::new(),
-                _type: PhantomData,
-            }
+            Self::empty(table, $kind)
         }
     };
 }
+
+/// SQLite's default limit on bound parameters in a single statement, since 3.32.0.
+pub const SQLITE_MAX_VARIABLE_NUMBER: usize = 32766;
+
 pub trait Veccable<T> {
     fn to_vec(self) -> Vec<T>;
 }
@@ -63,26 +139,116 @@ impl<T: Clone> Veccable<T> for (T, T, T) {
     }
 }
 
+/// The action for [`QueryBuilder::on_conflict`], which is superseded by
+/// [`QueryBuilder::on_conflict_do_nothing`] and [`QueryBuilder::on_conflict_do_update`].
+#[derive(Clone, Copy)]
 pub enum OnConflict {
     Ignore,
     Update,
 }
 
+/// What SQLite does when an insert or update violates a constraint, as in `INSERT OR IGNORE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConflictResolution {
+    Rollback,
+    Abort,
+    Fail,
+    Ignore,
+    Replace,
+}
+
+impl ConflictResolution {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ConflictResolution::Rollback => "ROLLBACK",
+            ConflictResolution::Abort => "ABORT",
+            ConflictResolution::Fail => "FAIL",
+            ConflictResolution::Ignore => "IGNORE",
+            ConflictResolution::Replace => "REPLACE",
+        }
+    }
+}
+
+#[derive(Clone)]
+struct ConflictClause<'a, C: Column> {
+    target: Vec<C>,
+    // the WHERE of a partial unique index, so the database can tell which index is meant
+    target_filter: Option<Expr<'a, C>>,
+    action: OnConflict,
+    // conflicting rows that don't match are left alone by DO UPDATE
+    update_filter: Option<Expr<'a, C>>,
+}
+
+impl<'a, C: Column> ConflictClause<'a, C> {
+    fn new(target: Vec<C>, target_filter: Option<Expr<'a, C>>, action: OnConflict) -> Self {
+        Self {
+            target,
+            target_filter,
+            action,
+            update_filter: None,
+        }
+    }
+}
+
 pub struct QueryBuilder<'a, S, C: Column> {
+    ctes: Vec<Cte<'a>>,
     table: &'a str,
     kind: QueryKind,
     columns: Option<&'a [C]>,
+    joins: Vec<Join<'a>>,
     values: Option<Vec<(C, Value)>>,
-    set: Option<Vec<(C, Value)>>,
-    on_conflict: Option<(Vec<C>, OnConflict)>,
+    rows: Option<(&'a [C], Vec<Vec<Value>>)>,
+    set: Option<Vec<(C, ValueExpr<'a>)>>,
+    on_conflict: Option<ConflictClause<'a, C>>,
+    resolution: Option<ConflictResolution>,
     returning: Option<&'a [C]>,
     filter: Option<Expr<'a, C>>,
+    // an update or delete without a filter is refused unless this is set
+    all_rows: bool,
     limit: Option<i64>,
     offset: Option<i64>,
-    order_by: Vec<ColumnOrdering<C>>,
+    keyset: Option<Keyset>,
+    order_by: Vec<OrderTerm<'a, C>>,
+    aggregates: Vec<Aggregate<C>>,
+    // raw expressions in the select list, after the columns and aggregates
+    fragments: Vec<Fragment<'a>>,
+    group_by: Vec<C>,
+    having: Option<Expr<'a, Aggregate<C>>>,
+    compounds: Vec<(CompoundOperator, QueryBuilder<'a, Select, C>)>,
     _type: PhantomData<S>,
 }
 
+// not derived, as that would require the state marker to be `Clone` too
+impl<S, C: Column> Clone for QueryBuilder<'_, S, C> {
+    fn clone(&self) -> Self {
+        Self {
+            ctes: self.ctes.clone(),
+            table: self.table,
+            kind: self.kind,
+            columns: self.columns,
+            joins: self.joins.clone(),
+            values: self.values.clone(),
+            rows: self.rows.clone(),
+            set: self.set.clone(),
+            on_conflict: self.on_conflict.clone(),
+            resolution: self.resolution,
+            returning: self.returning,
+            filter: self.filter.clone(),
+            all_rows: self.all_rows,
+            limit: self.limit,
+            offset: self.offset,
+            keyset: self.keyset.clone(),
+            order_by: self.order_by.clone(),
+            aggregates: self.aggregates.clone(),
+            fragments: self.fragments.clone(),
+            group_by: self.group_by.clone(),
+            having: self.having.clone(),
+            compounds: self.compounds.clone(),
+            _type: PhantomData,
+        }
+    }
+}
+
 impl<'a, C: Column> QueryBuilder<'a, Select, C> {
     generic_builder!(select_from, QueryKind::Select);
 
@@ -91,8 +257,212 @@ impl<'a, C: Column> QueryBuilder<'a, Select, C> {
         self
     }
 
+    pub fn inner_join<D: Column>(self, table: &'a str, on: Expr<'a, D>) -> Self {
+        self.join(JoinKind::Inner, table, Some(on))
+    }
+
+    pub fn left_join<D: Column>(self, table: &'a str, on: Expr<'a, D>) -> Self {
+        self.join(JoinKind::Left, table, Some(on))
+    }
+
+    pub fn cross_join(self, table: &'a str) -> Self {
+        self.join::<C>(JoinKind::Cross, table, None)
+    }
+
+    fn join<D: Column>(mut self, kind: JoinKind, table: &'a str, on: Option<Expr<'a, D>>) -> Self {
+        self.joins.push(Join::new(kind, table, on.map(Expr::erase)));
+        self
+    }
+
+    /// Filter on the columns of a joined table.
+    pub fn filter_joined<D: Column>(self, expr: Expr<'a, D>) -> Self {
+        self.filter(Expr::Erased(Box::new(expr.erase())))
+    }
+
+    /// Add raw SQL to the select list, eg `sql!("julianday('now') - {} AS age", Post::CreatedAt)`.
+    pub fn select_sql(mut self, fragment: Fragment<'a>) -> Self {
+        self.fragments.push(fragment);
+        self
+    }
+
+    /// Add an aggregate to the select list, after any plain `columns`.
+    pub fn aggregate(mut self, aggregate: Aggregate<C>) -> Self {
+        self.aggregates.push(aggregate);
+        self
+    }
+
+    pub fn group_by(mut self, column: C) -> Self {
+        self.group_by.push(column);
+        self
+    }
+
+    pub fn having(mut self, expr: Expr<'a, Aggregate<C>>) -> Self {
+        if let Some(having) = self.having {
+            self.having = Some(having.and(expr));
+        } else {
+            self.having = Some(expr);
+        }
+
+        self
+    }
+
+    /// Combine with another select using `UNION`. Ordering, limit and offset on `self` apply
+    /// to the combined result, so they must not be set on `other`.
+    pub fn union(self, other: QueryBuilder<'a, Select, C>) -> Self {
+        self.compound(CompoundOperator::Union, other)
+    }
+
+    pub fn union_all(self, other: QueryBuilder<'a, Select, C>) -> Self {
+        self.compound(CompoundOperator::UnionAll, other)
+    }
+
+    pub fn intersect(self, other: QueryBuilder<'a, Select, C>) -> Self {
+        self.compound(CompoundOperator::Intersect, other)
+    }
+
+    pub fn except(self, other: QueryBuilder<'a, Select, C>) -> Self {
+        self.compound(CompoundOperator::Except, other)
+    }
+
+    fn compound(mut self, operator: CompoundOperator, other: QueryBuilder<'a, Select, C>) -> Self {
+        self.compounds.push((operator, other));
+        self
+    }
+
     pub fn order_by(mut self, ordering: ColumnOrdering<C>) -> Self {
-        self.order_by.push(ordering);
+        self.order_by.push(OrderTerm::Column(ordering));
+        self
+    }
+
+    /// Start after the row a cursor from [`QueryBuilder::fetch_cursor_page`] points at.
+    /// The query must have the same ordering as the one that produced the cursor.
+    pub fn paginate_after(mut self, cursor: &Cursor) -> Self {
+        self.keyset = Some(Keyset::After(cursor.clone()));
+        self
+    }
+
+    /// End before the row a cursor from [`QueryBuilder::fetch_cursor_page`] points at.
+    pub fn paginate_before(mut self, cursor: &Cursor) -> Self {
+        self.keyset = Some(Keyset::Before(cursor.clone()));
+        self
+    }
+
+    /// Fetch up to `per_page` rows with keyset pagination, which seeks past earlier pages
+    /// through the `ORDER BY` columns instead of counting through them like `OFFSET`.
+    ///
+    /// The ordering must be unique, eg end with the primary key, and its columns must be selected
+    /// and `NOT NULL`, as no row compares greater or less than a `NULL`.
+    pub async fn fetch_cursor_page<'e, 'c: 'e, E, S>(
+        mut self,
+        executor: E,
+        per_page: i64,
+    ) -> Result<CursorPage<S>, Error>
+    where
+        E: 'e + sqlx::Executor<'c>,
+        E::Database: Backend,
+        S: for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
+    {
+        let keys = keyset_columns(&self.order_by)?;
+        let backward = matches!(self.keyset, Some(Keyset::Before(_)));
+        let from_cursor = self.keyset.is_some();
+        let per_page = per_page.max(0);
+
+        // one more row than needed tells whether there's another page
+        self.limit = Some(per_page.saturating_add(1));
+        let (query, values, trace) = self.render_traced(E::Database::dialect())?;
+        let rows = E::Database::query(&query, values)
+            .fetch_all(executor)
+            .instrument(trace.span())
+            .await;
+        trace.finish(rows.as_ref().map_or(0, |rows| rows.len() as u64));
+
+        let mut rows = rows?;
+        let more = rows.len() as i64 > per_page;
+        rows.truncate(per_page as usize);
+        if backward {
+            rows.reverse();
+        }
+
+        let cursor_at = |row: &<E::Database as Database>::Row| -> Result<Cursor, Error> {
+            let values = keys
+                .iter()
+                .map(|(column, _)| E::Database::read_value(row, column.to_col_name()))
+                .collect::<Result<Vec<_>, _>>()?;
+            if values.iter().any(|value| matches!(value, Value::Null)) {
+                return Err(BuildError::NullCursorValue.into());
+            }
+
+            Ok(Cursor::encode(&values))
+        };
+
+        let first = rows.first().map(cursor_at).transpose()?;
+        let last = rows.last().map(cursor_at).transpose()?;
+        let (previous, next) = if backward {
+            (first.filter(|_| more), last)
+        } else {
+            (first.filter(|_| from_cursor), last.filter(|_| more))
+        };
+
+        let items = rows
+            .iter()
+            .map(|row| S::from_row(row))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(CursorPage {
+            items,
+            next,
+            previous,
+        })
+    }
+
+    /// Fetch page `page` of `per_page` rows, counting pages from 1, along with the total number of
+    /// rows the query matches. Set an ordering, or rows may move between pages.
+    ///
+    /// `page` and `per_page` are clamped to at least 1, and a page too far out to be addressed
+    /// comes back empty.
+    pub async fn fetch_page<'c, A, DB, S>(
+        self,
+        conn: A,
+        page: i64,
+        per_page: i64,
+    ) -> Result<Page<S>, Error>
+    where
+        A: sqlx::Acquire<'c, Database = DB>,
+        DB: Backend,
+        for<'x> &'x mut DB::Connection: sqlx::Executor<'x, Database = DB>,
+        S: for<'r> sqlx::FromRow<'r, DB::Row>,
+        (i64,): for<'r> sqlx::FromRow<'r, DB::Row>,
+    {
+        let page = page.max(1);
+        let per_page = per_page.max(1);
+        let mut conn = conn.acquire().await?;
+
+        let mut counted = self.clone();
+        counted.limit = None;
+        counted.offset = None;
+        let total = counted.count(&mut *conn).await?;
+
+        let items = self
+            .limit(per_page)
+            .offset((page - 1).saturating_mul(per_page))
+            .fetch_all(&mut *conn)
+            .await?;
+
+        let page_count = total / per_page + i64::from(total % per_page != 0);
+        Ok(Page {
+            items,
+            total,
+            page,
+            per_page,
+            page_count,
+            has_next: page < page_count,
+            has_prev: page > 1,
+        })
+    }
+
+    /// Order by raw SQL, including the direction, eg `sql!("length({}) DESC", Post::Title)`.
+    pub fn order_by_sql(mut self, fragment: Fragment<'a>) -> Self {
+        self.order_by.push(OrderTerm::Fragment(fragment));
         self
     }
 
@@ -105,6 +475,91 @@ impl<'a, C: Column> QueryBuilder<'a, Select, C> {
         self.offset = Some(offset);
         self
     }
+
+    /// Count the rows the query matches with `SELECT COUNT(*)`, without fetching them.
+    pub async fn count<'e, E>(mut self, executor: E) -> Result<i64, Error>
+    where
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        (i64,): for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
+    {
+        // the cursor's filter depends on the ordering, which is dropped below
+        if let Some(keyset) = self.keyset.take() {
+            self.apply_keyset(keyset)?;
+        }
+
+        // raw select expressions may aggregate too
+        let simple = self.aggregates.is_empty()
+            && self.fragments.is_empty()
+            && self.group_by.is_empty()
+            && self.having.is_none()
+            && self.compounds.is_empty()
+            && self.limit.is_none()
+            && self.offset.is_none();
+
+        if !simple {
+            // a HAVING without GROUP BY makes the whole query a single group, which databases only
+            // accept once the select list aggregates something
+            if self.having.is_some()
+                && self.group_by.is_empty()
+                && self.aggregates.is_empty()
+                && self.fragments.is_empty()
+            {
+                self.columns = None;
+                self.aggregates = vec![Aggregate::count_all()];
+            }
+
+            // grouped, limited or combined rows can't be counted by swapping the select list
+            return self
+                .fetch_wrapped_scalar(executor, "SELECT COUNT(*) FROM (", ") AS counted")
+                .await;
+        }
+
+        self.columns = None;
+        self.aggregates = vec![Aggregate::count_all()];
+        self.order_by.clear();
+        self.fetch_wrapped_scalar(executor, "", "").await
+    }
+
+    /// Check whether the query matches any rows with `SELECT EXISTS (...)`, without fetching them.
+    pub async fn exists<'e, E>(self, executor: E) -> Result<bool, Error>
+    where
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        (bool,): for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
+    {
+        self.fetch_wrapped_scalar(executor, "SELECT EXISTS (", ")")
+            .await
+    }
+
+    async fn fetch_wrapped_scalar<'e, E, O>(
+        self,
+        executor: E,
+        prefix: &str,
+        suffix: &str,
+    ) -> Result<O, Error>
+    where
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        (O,): for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
+    {
+        let table = self.table;
+        let qualify = !self.joins.is_empty();
+        let mut writer = SqlWriter::new(E::Database::dialect());
+        writer.push_str(prefix);
+        self.write_sql(&mut writer, qualify)?;
+        writer.push_str(suffix);
+
+        let (query, values) = writer.finish();
+        let trace = QueryTrace::new(QueryKind::Select.as_str(), table, &query, &values);
+        let row = E::Database::query(&query, values)
+            .fetch_one(executor)
+            .instrument(trace.span())
+            .await;
+        trace.finish(row.is_ok() as u64);
+        let (scalar,) = <(O,)>::from_row(&row?)?;
+        Ok(scalar)
+    }
 }
 
 impl<'a, C: Column> QueryBuilder<'a, Insert, C> {
@@ -120,53 +575,127 @@ impl<'a, C: Column> QueryBuilder<'a, Insert, C> {
         self
     }
 
+    /// Insert a row of defaults, with `DEFAULT VALUES`, when no value has been set.
+    pub fn default_values(mut self) -> Self {
+        self.values.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Insert multiple rows at once, each row holding one value per column in `columns`.
+    ///
+    /// Use [`QueryBuilder::execute_many`] to split the rows across several statements when
+    /// they would exceed [`SQLITE_MAX_VARIABLE_NUMBER`].
+    pub fn values_rows<R>(mut self, columns: &'a [C], rows: R) -> Self
+    where
+        R: IntoIterator<Item = Vec<Value>>,
+    {
+        let rows = rows.into_iter();
+        if let Some((_, existing)) = &mut self.rows {
+            existing.extend(rows);
+        } else {
+            self.rows = Some((columns, rows.collect()));
+        }
+
+        self
+    }
+
+    /// Resolve constraint violations with `INSERT OR <resolution>`, eg skipping duplicate rows
+    /// in a bulk load with [`ConflictResolution::Ignore`].
+    pub fn or(mut self, resolution: ConflictResolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
+    pub fn on_conflict_do_nothing<CV: Veccable<C> + 'a>(mut self, columns: CV) -> Self {
+        self.on_conflict = Some(ConflictClause::new(
+            columns.to_vec(),
+            None,
+            OnConflict::Ignore,
+        ));
+        self
+    }
+
+    /// Like [`QueryBuilder::on_conflict_do_nothing`], for a conflict on a partial unique index
+    /// that only covers rows matching `filter`.
+    pub fn on_conflict_do_nothing_where<CV: Veccable<C> + 'a>(
+        mut self,
+        columns: CV,
+        filter: Expr<'a, C>,
+    ) -> Self {
+        let clause = ConflictClause::new(columns.to_vec(), Some(filter), OnConflict::Ignore);
+        self.on_conflict = Some(clause);
+        self
+    }
+
+    /// Add an `ON CONFLICT (...)` clause with either action. An `Update` without any `set` values
+    /// fails to build instead of failing to compile.
+    #[deprecated(note = "use `on_conflict_do_nothing` or `on_conflict_do_update`")]
     pub fn on_conflict<CV: Veccable<C> + 'a>(
         mut self,
         columns: CV,
         on_conflict: OnConflict,
+    ) -> QueryBuilder<'a, Upsert, C> {
+        self.on_conflict = Some(ConflictClause::new(columns.to_vec(), None, on_conflict));
+        self.into_state()
+    }
+
+    /// Add an `ON CONFLICT (...) DO UPDATE` clause. At least one value has to be `set` before the query can be executed.
+    pub fn on_conflict_do_update<CV: Veccable<C> + 'a>(
+        mut self,
+        columns: CV,
     ) -> QueryBuilder<'a, OnConflicted, C> {
-        self.on_conflict = Some((columns.to_vec(), on_conflict));
-        QueryBuilder {
-            table: self.table,
-            kind: self.kind,
-            columns: self.columns,
-            filter: self.filter,
-            limit: self.limit,
-            offset: self.offset,
-            values: self.values,
-            set: self.set,
-            on_conflict: self.on_conflict,
-            returning: self.returning,
-            order_by: self.order_by,
-            _type: PhantomData,
-        }
+        self.on_conflict = Some(ConflictClause::new(
+            columns.to_vec(),
+            None,
+            OnConflict::Update,
+        ));
+        self.into_state()
     }
 
-    pub fn returning(mut self, columns: &'a [C]) -> Self {
-        self.returning = Some(columns);
-        self
+    /// Like [`QueryBuilder::on_conflict_do_update`], for a conflict on a partial unique index
+    /// that only covers rows matching `filter`.
+    pub fn on_conflict_do_update_where<CV: Veccable<C> + 'a>(
+        mut self,
+        columns: CV,
+        filter: Expr<'a, C>,
+    ) -> QueryBuilder<'a, OnConflicted, C> {
+        let clause = ConflictClause::new(columns.to_vec(), Some(filter), OnConflict::Update);
+        self.on_conflict = Some(clause);
+        self.into_state()
     }
 }
 
 impl<'a, C: Column> QueryBuilder<'a, OnConflicted, C> {
-    /// Only valid after `on_conflict`
-    pub fn set<V: Into<Value>>(mut self, column: C, value: V) -> Self {
-        if !self.on_conflict.is_some() {
-            // todo: should use typestate pattern to prevent this at compile time
-            panic!("Cannot set value without an ON CONFLICT clause");
-        }
+    pub fn set<V: Into<Value>>(self, column: C, value: V) -> QueryBuilder<'a, Upsert, C> {
+        self.into_state::<Upsert>().set(column, value)
+    }
 
-        let value = value.into();
-        if let Some(set) = &mut self.set {
-            set.push((column, value));
-        } else {
-            self.set = Some(vec![(column, value)]);
-        }
+    /// Set a column to an expression, which can refer to the existing row by column and to the row
+    /// that failed to insert with [`ValueExpr::excluded`].
+    pub fn set_expr(self, column: C, expr: ValueExpr<'a>) -> QueryBuilder<'a, Upsert, C> {
+        self.into_state::<Upsert>().set_expr(column, expr)
+    }
+}
+
+impl<'a, C: Column> QueryBuilder<'a, Upsert, C> {
+    pub fn set<V: Into<Value>>(self, column: C, value: V) -> Self {
+        self.set_expr(column, ValueExpr::Value(value.into()))
+    }
+
+    pub fn set_expr(mut self, column: C, expr: ValueExpr<'a>) -> Self {
+        self.push_set(column, expr);
         self
     }
 
-    pub fn returning(mut self, columns: &'a [C]) -> Self {
-        self.returning = Some(columns);
+    /// Only update conflicting rows that match `expr`, leaving the others as they are.
+    pub fn update_where(mut self, expr: Expr<'a, C>) -> Self {
+        if let Some(clause) = &mut self.on_conflict {
+            clause.update_filter = Some(match clause.update_filter.take() {
+                Some(filter) => filter.and(expr),
+                None => expr,
+            });
+        }
+
         self
     }
 }
@@ -174,18 +703,26 @@ impl<'a, C: Column> QueryBuilder<'a, OnConflicted, C> {
 impl<'a, C: Column> QueryBuilder<'a, Update, C> {
     generic_builder!(update, QueryKind::Update);
 
+    /// Resolve constraint violations with `UPDATE OR <resolution>`.
+    pub fn or(mut self, resolution: ConflictResolution) -> Self {
+        self.resolution = Some(resolution);
+        self
+    }
+
     pub fn set<V: Into<Value>>(mut self, column: C, value: V) -> Self {
-        let value = value.into();
-        if let Some(set) = &mut self.set {
-            set.push((column, value));
-        } else {
-            self.set = Some(vec![(column, value)]);
-        }
+        self.push_set(column, ValueExpr::Value(value.into()));
         self
     }
 
-    pub fn returning(mut self, columns: &'a [C]) -> Self {
-        self.returning = Some(columns);
+    /// Set a column to an expression, eg `ValueExpr::col(Post::Views) + 1`.
+    pub fn set_expr(mut self, column: C, expr: ValueExpr<'a>) -> Self {
+        self.push_set(column, expr);
+        self
+    }
+
+    /// Allow the update to run without a filter, changing every row in the table.
+    pub fn all_rows(mut self) -> Self {
+        self.all_rows = true;
         self
     }
 }
@@ -193,22 +730,14 @@ impl<'a, C: Column> QueryBuilder<'a, Update, C> {
 impl<'a, C: Column> QueryBuilder<'a, Delete, C> {
     generic_builder!(delete_from, QueryKind::Delete);
 
-    pub fn returning(mut self, columns: &'a [C]) -> Self {
-        self.returning = Some(columns);
+    /// Allow the delete to run without a filter, removing every row in the table.
+    pub fn all_rows(mut self) -> Self {
+        self.all_rows = true;
         self
     }
 }
 
-macro_rules! assert_kind {
-    ($self:ident, $kind:pat) => {
-        match $self.kind {
-            $kind => {}
-            _ => panic!("Wrong query kind, expected {}", stringify!($kind)),
-        }
-    };
-}
-
-impl<'a, T, C: Column> QueryBuilder<'a, T, C> {
+impl<'a, T: Filterable, C: Column> QueryBuilder<'a, T, C> {
     pub fn filter(mut self, expr: Expr<'a, C>) -> Self {
         if let Some(where_clause) = self.filter {
             self.filter = Some(where_clause.and(expr));
@@ -218,242 +747,733 @@ impl<'a, T, C: Column> QueryBuilder<'a, T, C> {
 
         self
     }
+}
+
+impl<'a, T: Returnable, C: Column> QueryBuilder<'a, T, C> {
+    pub fn returning(mut self, columns: &'a [C]) -> Self {
+        self.returning = Some(columns);
+        self
+    }
+}
+
+impl<'a, T, C: Column> QueryBuilder<'a, T, C> {
+    fn empty(table: &'a str, kind: QueryKind) -> Self {
+        Self {
+            ctes: Vec::new(),
+            table,
+            kind,
+            columns: None,
+            joins: Vec::new(),
+            filter: None,
+            all_rows: false,
+            limit: None,
+            offset: None,
+            keyset: None,
+            values: None,
+            rows: None,
+            set: None,
+            on_conflict: None,
+            resolution: None,
+            returning: None,
+            order_by: Vec::new(),
+            aggregates: Vec::new(),
+            fragments: Vec::new(),
+            group_by: Vec::new(),
+            having: None,
+            compounds: Vec::new(),
+            _type: PhantomData,
+        }
+    }
 
-    pub fn to_sql(self) -> (String, Vec<Value>) {
-        let mut values = Vec::new();
-        let mut query = match self.kind {
-            QueryKind::Select => "SELECT ".to_string(),
-            QueryKind::Insert => "INSERT INTO ".to_string(),
-            QueryKind::Update => "UPDATE ".to_string(),
-            QueryKind::Delete => "DELETE ".to_string(),
+    fn push_set(&mut self, column: C, expr: ValueExpr<'a>) {
+        if let Some(set) = &mut self.set {
+            set.push((column, expr));
+        } else {
+            self.set = Some(vec![(column, expr)]);
+        }
+    }
+
+    /// Narrow the query to the rows on the far side of a pagination cursor.
+    fn apply_keyset(&mut self, keyset: Keyset) -> Result<(), BuildError> {
+        let (cursor, backward) = match keyset {
+            Keyset::After(cursor) => (cursor, false),
+            Keyset::Before(cursor) => (cursor, true),
         };
 
+        let mut keys = keyset_columns(&self.order_by)?;
+        let values = cursor.decode()?;
+        if values.len() != keys.len() {
+            return Err(BuildError::InvalidCursor);
+        }
+
+        // `col > NULL` matches nothing, which would silently end the pagination
+        if values.iter().any(|value| matches!(value, Value::Null)) {
+            return Err(BuildError::NullCursorValue);
+        }
+
+        if backward {
+            // walk the ordering in reverse, fetch_cursor_page flips the rows back afterwards
+            for (_, desc) in &mut keys {
+                *desc = !*desc;
+            }
+
+            self.order_by = keys
+                .iter()
+                .map(|&(column, desc)| {
+                    OrderTerm::Column(if desc {
+                        ColumnOrdering::Desc(column)
+                    } else {
+                        ColumnOrdering::Asc(column)
+                    })
+                })
+                .collect();
+        }
+
+        let expr = keyset_filter(&keys, values);
+        self.filter = Some(match self.filter.take() {
+            Some(filter) => filter.and(expr),
+            None => expr,
+        });
+
+        Ok(())
+    }
+
+    fn into_state<U>(self) -> QueryBuilder<'a, U, C> {
+        QueryBuilder {
+            ctes: self.ctes,
+            table: self.table,
+            kind: self.kind,
+            columns: self.columns,
+            joins: self.joins,
+            filter: self.filter,
+            all_rows: self.all_rows,
+            limit: self.limit,
+            offset: self.offset,
+            keyset: self.keyset,
+            values: self.values,
+            rows: self.rows,
+            set: self.set,
+            on_conflict: self.on_conflict,
+            resolution: self.resolution,
+            returning: self.returning,
+            order_by: self.order_by,
+            aggregates: self.aggregates,
+            fragments: self.fragments,
+            group_by: self.group_by,
+            having: self.having,
+            compounds: self.compounds,
+            _type: PhantomData,
+        }
+    }
+
+    /// Define a common table expression that the query can select from or join against by `name`.
+    pub fn with<D: Column + 'a>(
+        mut self,
+        name: &'a str,
+        query: QueryBuilder<'a, Select, D>,
+    ) -> Self {
+        self.ctes.push(Cte::new(name, Subquery::new(query), None));
+        self
+    }
+
+    /// Define a recursive common table expression as `base UNION ALL recursive`,
+    /// where `recursive` refers back to the expression by `name`.
+    pub fn with_recursive<D: Column + 'a>(
+        mut self,
+        name: &'a str,
+        base: QueryBuilder<'a, Select, D>,
+        recursive: QueryBuilder<'a, Select, D>,
+    ) -> Self {
+        let cte = Cte::new(name, Subquery::new(base), Some(Subquery::new(recursive)));
+        self.ctes.push(cte);
+        self
+    }
+
+    pub(crate) fn write_sql(mut self, w: &mut SqlWriter, qualify: bool) -> Result<(), BuildError> {
+        match self.kind {
+            QueryKind::Update if self.set.is_none() => return Err(BuildError::EmptyUpdate),
+            QueryKind::Update | QueryKind::Delete if self.filter.is_none() && !self.all_rows => {
+                return Err(BuildError::Unfiltered);
+            }
+            QueryKind::Insert => match (&self.values, &self.rows) {
+                (Some(_), Some(_)) => return Err(BuildError::MixedInsertValues),
+                (None, None) => return Err(BuildError::EmptyInsert),
+                (None, Some((columns, rows))) if columns.is_empty() || rows.is_empty() => {
+                    return Err(BuildError::EmptyInsert);
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+
+        if let Some(clause) = &self.on_conflict
+            && matches!(clause.action, OnConflict::Update)
+            && self.set.is_none()
+        {
+            return Err(BuildError::EmptyUpdate);
+        }
+
+        if let Some(keyset) = self.keyset.take() {
+            self.apply_keyset(keyset)?;
+        }
+
+        if self.returning.is_some() && !w.dialect().supports_returning() {
+            return Err(BuildError::Unsupported("RETURNING"));
+        }
+
+        if self.resolution.is_some() && !w.dialect().supports_conflict_resolution() {
+            return Err(BuildError::Unsupported("OR <conflict resolution>"));
+        }
+
+        // nested queries decide qualification for themselves, so restore the outer setting after
+        let outer_qualify = w.qualify;
+        w.qualify = qualify;
+
+        if !self.ctes.is_empty() {
+            // RECURSIVE applies to the whole WITH clause, not individual expressions
+            if self.ctes.iter().any(|cte| cte.is_recursive()) {
+                w.push_str("WITH RECURSIVE ");
+            } else {
+                w.push_str("WITH ");
+            }
+
+            try_push_separated(w, self.ctes.into_iter(), |w, cte| cte.write(w))?;
+            w.push(' ');
+        }
+
+        w.push_str(match self.kind {
+            QueryKind::Select => "SELECT ",
+            QueryKind::Insert => "INSERT ",
+            QueryKind::Update => "UPDATE ",
+            QueryKind::Delete => "DELETE ",
+        });
+
+        if let Some(resolution) = self.resolution {
+            w.push_str("OR ");
+            w.push_str(resolution.as_str());
+            w.push(' ');
+        }
+
+        if self.kind == QueryKind::Insert {
+            w.push_str("INTO ");
+        }
+
         if let Some(columns) = self.columns {
-            assert_kind!(self, QueryKind::Select | QueryKind::Insert);
-            push_separated(&mut query, columns.iter(), |query, column| {
-                column.write(query);
-            });
-        } else if matches!(self.kind, QueryKind::Select) {
-            query.push_str("*");
+            push_separated(w, columns.iter(), |w, column| w.push_column(column));
+            if !self.aggregates.is_empty() || !self.fragments.is_empty() {
+                w.push_str(", ");
+            }
+        } else if matches!(self.kind, QueryKind::Select)
+            && self.aggregates.is_empty()
+            && self.fragments.is_empty()
+        {
+            w.push('*');
         }
 
+        push_separated(w, self.aggregates.iter(), |w, aggregate| {
+            w.push_column(aggregate)
+        });
+        if !self.aggregates.is_empty() && !self.fragments.is_empty() {
+            w.push_str(", ");
+        }
+
+        try_push_separated(w, self.fragments.into_iter(), |w, fragment| {
+            fragment.write(w)
+        })?;
+
         match self.kind {
             QueryKind::Select => {
-                query.push_str(" FROM ");
-                query.push_str(self.table);
+                w.push_str(" FROM ");
+                w.push_str(self.table);
+                for join in self.joins {
+                    join.write(w)?;
+                }
             }
             QueryKind::Insert => {
-                query.push_str(self.table);
+                w.push_str(self.table);
             }
             QueryKind::Update => {
-                query.push_str(self.table);
+                w.push_str(self.table);
             }
             QueryKind::Delete => {
-                query.push_str(" FROM ");
-                query.push_str(self.table);
+                w.push_str(" FROM ");
+                w.push_str(self.table);
             }
         }
 
-        if let Some(sql_values) = self.values {
-            assert_kind!(self, QueryKind::Insert);
-            query.push_str(" (");
-            push_separated(&mut query, sql_values.iter(), |query, (column, _)| {
-                column.write(query);
-            });
-            query.push_str(") VALUES (");
-            push_separated(&mut query, sql_values.into_iter(), |query, (_, value)| {
-                query.push_str("?");
-                values.push(value);
-            });
-            query.push_str(")");
+        match self.values {
+            Some(sql_values) if sql_values.is_empty() => w.push_str(w.dialect().default_values()),
+            Some(sql_values) => {
+                w.push_str(" (");
+                push_separated(w, sql_values.iter(), |w, (column, _)| column.write(w));
+                w.push_str(") VALUES (");
+                try_push_separated(w, sql_values.into_iter(), |w, (_, value)| {
+                    w.push_value(value)
+                })?;
+                w.push(')');
+            }
+            None => {}
         }
 
-        // match is necessary to prevent .set from being consumed
-        match self.kind {
-            QueryKind::Update => {
-                if let Some(set) = self.set {
-                    assert_kind!(self, QueryKind::Update);
-                    query.push_str(" SET ");
-                    push_separated(&mut query, set.into_iter(), |query, (column, value)| {
-                        column.write(query);
-                        query.push_str(" = ?");
-                        values.push(value);
+        if let Some((columns, rows)) = self.rows {
+            w.push_str(" (");
+            push_separated(w, columns.iter(), |w, column| column.write(w));
+            w.push_str(") VALUES ");
+            try_push_separated(w, rows.into_iter(), |w, row| {
+                if row.len() != columns.len() {
+                    return Err(BuildError::RowLength {
+                        expected: columns.len(),
+                        found: row.len(),
                     });
                 }
-            }
-            QueryKind::Insert => {
-                if let Some(on_conflict) = self.on_conflict {
-                    assert_kind!(self, QueryKind::Insert);
-                    query.push_str(" ON CONFLICT (");
-                    push_separated(&mut query, on_conflict.0.iter(), |query, column| {
-                        column.write(query);
-                    });
-                    query.push_str(") ");
-                    match on_conflict.1 {
-                        OnConflict::Ignore => query.push_str("DO NOTHING"),
-                        OnConflict::Update => {
-                            query.push_str("DO UPDATE SET ");
-                            // todo: should be enforced with typeset
-                            let set = self.set.expect("SET clause is required for UPDATE");
-                            push_separated(
-                                &mut query,
-                                set.into_iter(),
-                                |query, (column, value)| {
-                                    column.write(query);
-                                    query.push_str(" = ?");
-                                    values.push(value);
-                                },
-                            );
-                        }
+
+                w.push('(');
+                try_push_separated(w, row.into_iter(), |w, value| w.push_value(value))?;
+                w.push(')');
+                Ok(())
+            })?;
+        }
+
+        let mut set_keyword = " SET ";
+        let mut update_filter = None;
+        if let Some(clause) = self.on_conflict {
+            let columns = clause.target;
+            update_filter = clause.update_filter;
+            match w.dialect().upsert_style() {
+                UpsertStyle::OnConflict => {
+                    w.push_str(" ON CONFLICT (");
+                    push_separated(w, columns.iter(), |w, column| column.write(w));
+                    w.push(')');
+                    if let Some(filter) = clause.target_filter {
+                        // has to match the index's WHERE, which the database can't check against a placeholder
+                        w.push_str(" WHERE ");
+                        w.inline_values = true;
+                        filter.write(w)?;
+                        w.inline_values = false;
+                    }
+                    match clause.action {
+                        OnConflict::Ignore => w.push_str(" DO NOTHING"),
+                        OnConflict::Update => w.push_str(" DO UPDATE"),
                     }
                 }
+                UpsertStyle::OnDuplicateKey
+                    if clause.target_filter.is_some() || update_filter.is_some() =>
+                {
+                    return Err(BuildError::Unsupported("WHERE in a conflict clause"));
+                }
+                // the conflict target is implied by whichever unique key was violated
+                UpsertStyle::OnDuplicateKey => match clause.action {
+                    OnConflict::Ignore => {
+                        // there's no DO NOTHING, but assigning a column to itself changes nothing
+                        if let Some(column) = columns.first() {
+                            w.push_str(" ON DUPLICATE KEY UPDATE ");
+                            column.write(w);
+                            w.push_str(" = ");
+                            column.write(w);
+                        }
+                    }
+                    OnConflict::Update => set_keyword = " ON DUPLICATE KEY UPDATE ",
+                },
             }
-            _ => {
-                assert!(
-                    self.set.is_none(),
-                    "SET clause is not allowed in this query kind"
-                );
-            }
+        }
+
+        // for an insert this is the DO UPDATE of the conflict clause, for an update it's the main SET
+        if let Some(set) = self.set {
+            w.push_str(set_keyword);
+            try_push_separated(w, set.into_iter(), |w, (column, expr)| {
+                column.write(w);
+                w.push_str(" = ");
+                expr.write(w)
+            })?;
+        }
+
+        if let Some(filter) = update_filter {
+            w.push_str(" WHERE ");
+            filter.write(w)?;
         }
 
         if let Some(where_clause) = self.filter {
-            assert_kind!(
-                self,
-                QueryKind::Select | QueryKind::Update | QueryKind::Delete
-            );
-            query.push_str(" WHERE ");
-            where_clause.write(&mut query, &mut values);
+            w.push_str(" WHERE ");
+            where_clause.write(w)?;
+        }
+
+        if !self.group_by.is_empty() {
+            w.push_str(" GROUP BY ");
+            push_separated(w, self.group_by.iter(), |w, column| w.push_column(column));
+        }
+
+        if let Some(having) = self.having {
+            w.push_str(" HAVING ");
+            having.write(w)?;
+        }
+
+        for (operator, part) in self.compounds {
+            if !part.order_by.is_empty()
+                || part.limit.is_some()
+                || part.offset.is_some()
+                || !part.ctes.is_empty()
+            {
+                return Err(BuildError::CompoundPartClause);
+            }
+
+            operator.write(w);
+            let qualify = !part.joins.is_empty();
+            if part.compounds.is_empty() {
+                part.write_sql(w, qualify)?;
+            } else {
+                // compound operators chain left to right, so a nested compound is selected from
+                // as a whole instead of being merged into this one
+                w.push_str("SELECT * FROM (");
+                part.write_sql(w, qualify)?;
+                w.push_str(") AS compounded");
+            }
         }
 
         if !self.order_by.is_empty() {
-            assert_kind!(self, QueryKind::Select);
-            query.push_str(" ORDER BY ");
-            push_separated(&mut query, self.order_by.iter(), |query, ordering| {
-                ordering.write(query);
-            });
+            w.push_str(" ORDER BY ");
+            try_push_separated(w, self.order_by.into_iter(), |w, term| term.write(w))?;
         }
 
         if let Some(limit) = self.limit {
-            assert_kind!(self, QueryKind::Select);
-            query.push_str(" LIMIT ");
-            query.push_str(&limit.to_string());
+            w.push_str(" LIMIT ");
+            w.push_str(&limit.to_string());
         }
 
         if let Some(offset) = self.offset {
-            assert_kind!(self, QueryKind::Select);
-            query.push_str(" OFFSET ");
-            query.push_str(&offset.to_string());
+            w.push_str(" OFFSET ");
+            w.push_str(&offset.to_string());
         }
 
         if let Some(returning) = self.returning {
-            assert_kind!(
-                self,
-                QueryKind::Insert | QueryKind::Update | QueryKind::Delete
-            );
-            query.push_str(" RETURNING ");
-            push_separated(&mut query, returning.iter(), |query, column| {
-                column.write(query)
-            });
+            w.push_str(" RETURNING ");
+            push_separated(w, returning.iter(), |w, column| column.write(w));
         }
 
-        (query, values)
+        w.qualify = outer_qualify;
+        Ok(())
+    }
+}
+
+impl<'a, T: Executable, C: Column> QueryBuilder<'a, T, C> {
+    /// Render the query for SQLite.
+    pub fn to_sql(self) -> Result<(String, Vec<Value>), BuildError> {
+        self.to_sql_for(&Sqlite)
+    }
+
+    /// Render the query for the given dialect.
+    pub fn to_sql_for(self, dialect: &dyn Dialect) -> Result<(String, Vec<Value>), BuildError> {
+        // columns have to be qualified once another table is in scope, or shared names like "id" are ambiguous
+        let qualify = !self.joins.is_empty();
+        let mut writer = SqlWriter::new(dialect);
+        self.write_sql(&mut writer, qualify)?;
+        Ok(writer.finish())
+    }
+
+    /// Render the query for a database and start tracing it.
+    fn render_traced(
+        self,
+        dialect: &dyn Dialect,
+    ) -> Result<(String, Vec<Value>, QueryTrace), BuildError> {
+        let (kind, table) = (self.kind, self.table);
+        let (query, values) = self.to_sql_for(dialect)?;
+        let trace = QueryTrace::new(kind.as_str(), table, &query, &values);
+        Ok((query, values, trace))
     }
 
-    pub async fn fetch_one<'e, E, S>(mut self, executor: E) -> Result<S, sqlx::Error>
+    pub async fn fetch_one<'e, E, S>(mut self, executor: E) -> Result<S, Error>
     where
-        E: 'e + sqlx::Executor<'e, Database = sqlx::Sqlite>,
-        S: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow>,
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        S: for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
     {
         if self.limit.is_none() && self.kind == QueryKind::Select {
             self.limit = Some(1);
         }
 
-        let (query, values) = self.to_sql();
-        let mut query = sqlx::query(&query);
-        for value in values.into_iter() {
-            query = value.bind_to(query);
-        }
-
-        query
+        let (query, values, trace) = self.render_traced(E::Database::dialect())?;
+        let row = E::Database::query(&query, values)
             .fetch_one(executor)
-            .await
-            .and_then(|row| S::from_row(&row))
+            .instrument(trace.span())
+            .await;
+        trace.finish(row.is_ok() as u64);
+        Ok(S::from_row(&row?)?)
     }
 
-    pub async fn fetch_optional<'e, 'c: 'e, E, S>(
-        mut self,
-        executor: E,
-    ) -> Result<Option<S>, sqlx::Error>
+    pub async fn fetch_optional<'e, 'c: 'e, E, S>(mut self, executor: E) -> Result<Option<S>, Error>
     where
-        E: 'e + sqlx::Executor<'c, Database = sqlx::Sqlite>,
-        S: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow>,
+        E: 'e + sqlx::Executor<'c>,
+        E::Database: Backend,
+        S: for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
     {
         if self.limit.is_none() && self.kind == QueryKind::Select {
             self.limit = Some(1);
         }
 
-        let (query, values) = self.to_sql();
-        let mut query = sqlx::query(&query);
-        for value in values.into_iter() {
-            query = value.bind_to(query);
+        let (query, values, trace) = self.render_traced(E::Database::dialect())?;
+        let row = E::Database::query(&query, values)
+            .fetch_optional(executor)
+            .instrument(trace.span())
+            .await;
+        trace.finish(matches!(row, Ok(Some(_))) as u64);
+        match row? {
+            Some(row) => Ok(Some(S::from_row(&row)?)),
+            None => Ok(None),
         }
+    }
 
-        query
-            .fetch_optional(executor)
+    /// Fetch the first column of the first row, eg the result of an aggregate.
+    pub async fn fetch_scalar<'e, E, O>(self, executor: E) -> Result<O, Error>
+    where
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        (O,): for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
+    {
+        self.fetch_one::<E, (O,)>(executor)
             .await
-            .and_then(|row| match row {
-                Some(row) => S::from_row(&row).map(Some),
-                None => Ok(None),
-            })
+            .map(|(scalar,)| scalar)
     }
 
-    pub async fn fetch_all<'e, 'c: 'e, E, S>(self, executor: E) -> Result<Vec<S>, sqlx::Error>
+    pub async fn fetch_all<'e, 'c: 'e, E, S>(self, executor: E) -> Result<Vec<S>, Error>
     where
-        E: 'e + sqlx::Executor<'c, Database = sqlx::Sqlite>,
-        S: for<'r> sqlx::FromRow<'r, sqlx::sqlite::SqliteRow>,
+        E: 'e + sqlx::Executor<'c>,
+        E::Database: Backend,
+        S: for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
     {
-        let (query, values) = self.to_sql();
-        let mut query = sqlx::query(&query);
-        for value in values.into_iter() {
-            query = value.bind_to(query);
-        }
+        let (query, values, trace) = self.render_traced(E::Database::dialect())?;
+        let rows = E::Database::query(&query, values)
+            .fetch_all(executor)
+            .instrument(trace.span())
+            .await;
+        trace.finish(rows.as_ref().map_or(0, |rows| rows.len() as u64));
+        Ok(rows?
+            .into_iter()
+            .map(|row| S::from_row(&row))
+            .collect::<Result<Vec<_>, _>>()?)
+    }
 
-        query.fetch_all(executor).await.and_then(|rows| {
-            rows.into_iter()
-                .map(|row| S::from_row(&row))
-                .collect::<Result<Vec<_>, _>>()
-        })
+    /// Stream the rows as they are read, instead of collecting them like [`QueryBuilder::fetch_all`].
+    pub fn fetch<'e, 'c: 'e, E, S>(
+        self,
+        executor: E,
+    ) -> impl Stream<Item = Result<S, Error>> + use<'e, T, C, E, S>
+    where
+        E: 'e + sqlx::Executor<'c>,
+        E::Database: Backend,
+        S: 'e + for<'r> sqlx::FromRow<'r, <E::Database as Database>::Row>,
+    {
+        let rendered = self.render_traced(E::Database::dialect());
+        async_stream::try_stream! {
+            let (query, values, trace) = rendered?;
+            let mut rows = E::Database::query(&query, values).fetch(executor);
+            let mut count = 0;
+            while let Some(row) = rows.try_next().instrument(trace.span()).await? {
+                count += 1;
+                yield S::from_row(&row)?;
+            }
+
+            // a stream that is dropped early or fails part way through never reports its row count
+            trace.finish(count);
+        }
     }
 
     pub async fn execute<'e, 'c: 'e, E>(
         self,
         executor: E,
-    ) -> Result<sqlx::sqlite::SqliteQueryResult, sqlx::Error>
+    ) -> Result<<E::Database as Database>::QueryResult, Error>
+    where
+        E: 'e + sqlx::Executor<'c>,
+        E::Database: Backend,
+    {
+        let (query, values, trace) = self.render_traced(E::Database::dialect())?;
+        let result = E::Database::query(&query, values)
+            .execute(executor)
+            .instrument(trace.span())
+            .await;
+        trace.finish(result.as_ref().map_or(0, E::Database::rows_affected));
+        Ok(result?)
+    }
+
+    /// Run `EXPLAIN QUERY PLAN` on the query instead of executing it.
+    pub async fn explain<'e, 'c: 'e, E>(self, executor: E) -> Result<QueryPlan, Error>
+    where
+        E: 'e + sqlx::Executor<'c, Database = sqlx::Sqlite>,
+    {
+        let (kind, table) = (self.kind, self.table);
+        let (query, values) = self.to_sql()?;
+        let query = format!("EXPLAIN QUERY PLAN {query}");
+        let trace = QueryTrace::new(kind.as_str(), table, &query, &values);
+        let rows = sqlx::Sqlite::query(&query, values)
+            .fetch_all(executor)
+            .instrument(trace.span())
+            .await;
+        trace.finish(rows.as_ref().map_or(0, |rows| rows.len() as u64));
+        let rows = rows?
+            .iter()
+            .map(<(i64, i64, i64, String)>::from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(QueryPlan::from_rows(&rows))
+    }
+
+    /// Explain the query and, in debug builds, panic if it has a `WHERE` clause but still scans a whole table.
+    /// Meant for integration tests, to catch queries that need an index before they ship.
+    pub async fn assert_indexed<'e, 'c: 'e, E>(self, executor: E) -> Result<QueryPlan, Error>
     where
         E: 'e + sqlx::Executor<'c, Database = sqlx::Sqlite>,
     {
-        let (query, values) = self.to_sql();
-        let mut query = sqlx::query(&query);
-        for value in values.into_iter() {
-            query = value.bind_to(query);
+        let filtered = self.filter.is_some();
+        let plan = self.explain(executor).await?;
+        if cfg!(debug_assertions) && filtered {
+            let scans = plan.full_scans();
+            assert!(
+                scans.is_empty(),
+                "filtered query does a full table scan: {}",
+                scans
+                    .iter()
+                    .map(|node| node.detail.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        Ok(plan)
+    }
+}
+
+impl<'a, T: Insertable, C: Column> QueryBuilder<'a, T, C> {
+    /// Split a multi-row insert into statements that each stay under [`SQLITE_MAX_VARIABLE_NUMBER`].
+    pub fn to_sql_chunks(self) -> Result<Vec<(String, Vec<Value>)>, BuildError> {
+        self.to_sql_chunks_for(&Sqlite)
+    }
+
+    /// Split a multi-row insert into statements that each stay under the dialect's variable limit.
+    pub fn to_sql_chunks_for(
+        mut self,
+        dialect: &dyn Dialect,
+    ) -> Result<Vec<(String, Vec<Value>)>, BuildError> {
+        let Some((columns, rows)) = self.rows.take() else {
+            return Ok(vec![self.to_sql_for(dialect)?]);
+        };
+
+        if self.values.is_some() {
+            return Err(BuildError::MixedInsertValues);
+        }
+
+        // values bound outside the rows, eg in a CTE or the conflict clause, are repeated in every
+        // statement and count against the limit too, so measure them with a single placeholder row
+        let probe = self.chunk(columns, vec![vec![Value::Integer(0); columns.len()]]);
+        let reserved = probe.to_sql_for(dialect)?.1.len() - columns.len();
+        let limit = dialect.max_variables();
+        let available = limit.saturating_sub(reserved);
+        if available < columns.len().max(1) {
+            return Err(BuildError::TooManyVariables { reserved, limit });
+        }
+
+        let rows_per_chunk = available / columns.len().max(1);
+
+        let mut chunks = Vec::new();
+        let mut rows = rows.into_iter().peekable();
+        while rows.peek().is_some() {
+            let chunk = self.chunk(columns, rows.by_ref().take(rows_per_chunk).collect());
+            chunks.push(chunk.to_sql_for(dialect)?);
+        }
+
+        if chunks.is_empty() {
+            return Err(BuildError::EmptyInsert);
+        }
+
+        Ok(chunks)
+    }
+
+    // a statement inserting `rows`, with everything but the rows copied from this one
+    fn chunk(&self, columns: &'a [C], rows: Vec<Vec<Value>>) -> Self {
+        let mut chunk = Self::empty(self.table, self.kind);
+        chunk.ctes = self.ctes.clone();
+        chunk.rows = Some((columns, rows));
+        chunk.set = self.set.clone();
+        chunk.on_conflict = self.on_conflict.clone();
+        chunk.resolution = self.resolution;
+        chunk.returning = self.returning;
+        chunk
+    }
+
+    /// Execute a multi-row insert, splitting it into as many statements as necessary.
+    /// When `in_transaction` is set, every statement runs in a single transaction so a failure
+    /// part way through does not leave some of the rows inserted.
+    pub async fn execute_many<'c, A, DB>(
+        self,
+        conn: A,
+        in_transaction: bool,
+    ) -> Result<DB::QueryResult, Error>
+    where
+        A: sqlx::Acquire<'c, Database = DB>,
+        DB: Backend,
+        for<'x> &'x mut DB::Connection: sqlx::Executor<'x, Database = DB>,
+    {
+        let (kind, table) = (self.kind, self.table);
+        let chunks = self.to_sql_chunks_for(DB::dialect())?;
+        let mut result = DB::QueryResult::default();
+
+        if in_transaction {
+            let mut tx = conn.begin().await?;
+            for (query, values) in chunks {
+                let trace = QueryTrace::new(kind.as_str(), table, &query, &values);
+                let chunk = DB::query(&query, values)
+                    .execute(&mut *tx)
+                    .instrument(trace.span())
+                    .await;
+                trace.finish(chunk.as_ref().map_or(0, DB::rows_affected));
+                result.extend([chunk?]);
+            }
+
+            tx.commit().await?;
+        } else {
+            let mut conn = conn.acquire().await?;
+            for (query, values) in chunks {
+                let trace = QueryTrace::new(kind.as_str(), table, &query, &values);
+                let chunk = DB::query(&query, values)
+                    .execute(&mut *conn)
+                    .instrument(trace.span())
+                    .await;
+                trace.finish(chunk.as_ref().map_or(0, DB::rows_affected));
+                result.extend([chunk?]);
+            }
         }
 
-        query.execute(executor).await
+        Ok(result)
     }
 }
 
-fn push_separated<I, F>(query: &mut String, iter: I, mut cb: F)
+fn try_push_separated<I, F, E>(w: &mut SqlWriter, iter: I, mut cb: F) -> Result<(), E>
+where
+    I: Iterator,
+    F: FnMut(&mut SqlWriter, I::Item) -> Result<(), E>,
+{
+    let mut first = true;
+    for item in iter {
+        if !first {
+            w.push_str(", ");
+        } else {
+            first = false;
+        }
+        cb(w, item)?;
+    }
+
+    Ok(())
+}
+
+fn push_separated<I, F>(w: &mut SqlWriter, iter: I, mut cb: F)
 where
     I: Iterator,
-    F: FnMut(&mut String, I::Item),
+    F: FnMut(&mut SqlWriter, I::Item),
 {
     let mut first = true;
     for item in iter {
         if !first {
-            query.push_str(", ");
+            w.push_str(", ");
         } else {
             first = false;
         }
-        cb(query, item);
+        cb(w, item);
     }
 }
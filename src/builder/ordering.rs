@@ -1,5 +1,9 @@
+use super::fragment::Fragment;
+use super::writer::SqlWriter;
 use crate::column::Column;
+use crate::error::BuildError;
 
+#[derive(Clone)]
 pub enum ColumnOrdering<T: Column> {
     Asc(T),
     Desc(T),
@@ -10,32 +14,59 @@ pub enum ColumnOrdering<T: Column> {
 }
 
 impl<T: Column> ColumnOrdering<T> {
-    pub fn write(&self, f: &mut String) {
-        match self {
-            ColumnOrdering::Asc(column) => {
-                column.write(f);
-                f.push_str(" ASC");
-            }
-            ColumnOrdering::Desc(column) => {
-                column.write(f);
-                f.push_str(" DESC");
-            }
-            ColumnOrdering::AscNullsFirst(column) => {
-                column.write(f);
-                f.push_str(" ASC NULLS FIRST");
-            }
-            ColumnOrdering::AscNullsLast(column) => {
-                column.write(f);
-                f.push_str(" ASC NULLS LAST");
+    pub fn write(&self, w: &mut SqlWriter) {
+        let (column, direction, nulls_first) = match self {
+            ColumnOrdering::Asc(column) => (column, " ASC", None),
+            ColumnOrdering::Desc(column) => (column, " DESC", None),
+            ColumnOrdering::AscNullsFirst(column) => (column, " ASC", Some(true)),
+            ColumnOrdering::AscNullsLast(column) => (column, " ASC", Some(false)),
+            ColumnOrdering::DescNullsFirst(column) => (column, " DESC", Some(true)),
+            ColumnOrdering::DescNullsLast(column) => (column, " DESC", Some(false)),
+        };
+
+        match nulls_first {
+            Some(nulls_first) if !w.dialect().supports_nulls_ordering() => {
+                // `IS NULL` is 1 for nulls, so sorting on it first puts them before or after the rest
+                w.push_column(column);
+                w.push_str(if nulls_first {
+                    " IS NULL DESC, "
+                } else {
+                    " IS NULL ASC, "
+                });
+                w.push_column(column);
+                w.push_str(direction);
             }
-            ColumnOrdering::DescNullsFirst(column) => {
-                column.write(f);
-                f.push_str(" DESC NULLS FIRST");
+            Some(nulls_first) => {
+                w.push_column(column);
+                w.push_str(direction);
+                w.push_str(if nulls_first {
+                    " NULLS FIRST"
+                } else {
+                    " NULLS LAST"
+                });
             }
-            ColumnOrdering::DescNullsLast(column) => {
-                column.write(f);
-                f.push_str(" DESC NULLS LAST");
+            None => {
+                w.push_column(column);
+                w.push_str(direction);
             }
         }
     }
 }
+
+/// An entry of the `ORDER BY` clause.
+#[derive(Clone)]
+pub(crate) enum OrderTerm<'a, C: Column> {
+    Column(ColumnOrdering<C>),
+    Fragment(Fragment<'a>),
+}
+
+impl<C: Column> OrderTerm<'_, C> {
+    pub(crate) fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        match self {
+            OrderTerm::Column(ordering) => ordering.write(w),
+            OrderTerm::Fragment(fragment) => fragment.write(w)?,
+        }
+
+        Ok(())
+    }
+}
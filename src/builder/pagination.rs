@@ -0,0 +1,215 @@
+use super::expr::Expr;
+use super::fragment::{Argument, Fragment};
+use super::ordering::{ColumnOrdering, OrderTerm};
+use super::value::Value;
+use crate::column::Column;
+use crate::error::BuildError;
+use std::fmt;
+
+/// An opaque token marking a position in a keyset paginated query, handed out in a [`CursorPage`]
+/// and passed back to [`QueryBuilder::paginate_after`](super::QueryBuilder::paginate_after) or
+/// [`QueryBuilder::paginate_before`](super::QueryBuilder::paginate_before).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cursor(String);
+
+/// One page of an offset paginated query, from [`QueryBuilder::fetch_page`](super::QueryBuilder::fetch_page).
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// The number of rows across every page.
+    pub total: i64,
+    /// The page number, counting from 1.
+    pub page: i64,
+    pub per_page: i64,
+    pub page_count: i64,
+    pub has_next: bool,
+    pub has_prev: bool,
+}
+
+/// One page of a keyset paginated query.
+#[derive(Debug, Clone)]
+pub struct CursorPage<T> {
+    pub items: Vec<T>,
+    /// The cursor for the page after this one, if there is one.
+    pub next: Option<Cursor>,
+    /// The cursor for the page before this one, if there is one.
+    pub previous: Option<Cursor>,
+}
+
+#[derive(Clone)]
+pub(crate) enum Keyset {
+    After(Cursor),
+    Before(Cursor),
+}
+
+impl Cursor {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Encode the values of the ordering columns for a row as a hex string.
+    pub(crate) fn encode(values: &[Value]) -> Self {
+        let mut bytes = Vec::new();
+        for value in values {
+            match value {
+                Value::Null => bytes.push(b'n'),
+                Value::Bool(v) => bytes.extend([b'o', *v as u8]),
+                Value::Integer(v) => {
+                    bytes.push(b'i');
+                    bytes.extend(v.to_be_bytes());
+                }
+                Value::Real(v) => {
+                    bytes.push(b'r');
+                    bytes.extend(v.to_bits().to_be_bytes());
+                }
+                Value::String(v) => {
+                    bytes.push(b's');
+                    bytes.extend((v.len() as u32).to_be_bytes());
+                    bytes.extend(v.as_bytes());
+                }
+                Value::Blob(v) => {
+                    bytes.push(b'b');
+                    bytes.extend((v.len() as u32).to_be_bytes());
+                    bytes.extend(v);
+                }
+            }
+        }
+
+        Self(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+    }
+
+    pub(crate) fn decode(&self) -> Result<Vec<Value>, BuildError> {
+        if !self.0.len().is_multiple_of(2) || !self.0.is_ascii() {
+            return Err(BuildError::InvalidCursor);
+        }
+
+        let bytes = (0..self.0.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&self.0[i..i + 2], 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| BuildError::InvalidCursor)?;
+
+        let mut reader = Reader(&bytes);
+        let mut values = Vec::new();
+        while let Some(tag) = reader.take(1) {
+            values.push(match tag[0] {
+                b'n' => Value::Null,
+                b'o' => Value::Bool(reader.array::<1>()?[0] != 0),
+                b'i' => Value::Integer(i64::from_be_bytes(reader.array()?)),
+                b'r' => Value::Real(f64::from_bits(u64::from_be_bytes(reader.array()?))),
+                b's' => {
+                    let bytes = reader.sized()?;
+                    let string = String::from_utf8(bytes.to_vec());
+                    Value::String(string.map_err(|_| BuildError::InvalidCursor)?)
+                }
+                b'b' => Value::Blob(reader.sized()?.to_vec()),
+                _ => return Err(BuildError::InvalidCursor),
+            });
+        }
+
+        Ok(values)
+    }
+}
+
+struct Reader<'b>(&'b [u8]);
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, len: usize) -> Option<&'b [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], BuildError> {
+        let bytes = self.take(N).ok_or(BuildError::InvalidCursor)?;
+        Ok(bytes.try_into().expect("took N bytes"))
+    }
+
+    // a length prefixed string or blob
+    fn sized(&mut self) -> Result<&'b [u8], BuildError> {
+        let len = u32::from_be_bytes(self.array()?) as usize;
+        self.take(len).ok_or(BuildError::InvalidCursor)
+    }
+}
+
+impl From<String> for Cursor {
+    fn from(token: String) -> Self {
+        Self(token)
+    }
+}
+
+impl From<&str> for Cursor {
+    fn from(token: &str) -> Self {
+        Self(token.to_string())
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// The columns a keyset paginated query is ordered by, with whether each one is descending.
+pub(crate) fn keyset_columns<C: Column>(
+    order_by: &[OrderTerm<'_, C>],
+) -> Result<Vec<(C, bool)>, BuildError> {
+    if order_by.is_empty() {
+        return Err(BuildError::KeysetOrdering);
+    }
+
+    order_by
+        .iter()
+        .map(|term| match term {
+            OrderTerm::Column(ColumnOrdering::Asc(column)) => Ok((*column, false)),
+            OrderTerm::Column(ColumnOrdering::Desc(column)) => Ok((*column, true)),
+            _ => Err(BuildError::KeysetOrdering),
+        })
+        .collect()
+}
+
+/// Match the rows that come strictly after `values` when ordered by `keys`.
+pub(crate) fn keyset_filter<'a, C: Column>(keys: &[(C, bool)], values: Vec<Value>) -> Expr<'a, C> {
+    let descending = keys[0].1;
+    if keys.len() > 1 && keys.iter().all(|(_, desc)| *desc == descending) {
+        // a row value comparison when every column sorts the same way, which indexes handle well
+        let columns = vec!["{}"; keys.len()].join(", ");
+        let placeholders = vec!["?"; keys.len()].join(", ");
+        let operator = if descending { "<" } else { ">" };
+        let arguments = keys
+            .iter()
+            .map(|(column, _)| Argument::Column(column.to_ref()))
+            .chain(values.into_iter().map(Argument::Value))
+            .collect();
+
+        return Expr::Fragment(Fragment::new(
+            format!("({columns}) {operator} ({placeholders})"),
+            arguments,
+        ));
+    }
+
+    // otherwise (a > x) OR (a = x AND b < y) OR ..., flipping the comparison for descending columns
+    let mut filter: Option<Expr<'a, C>> = None;
+    for (i, (column, desc)) in keys.iter().enumerate() {
+        let mut branch = if *desc {
+            Expr::Lt(*column, values[i].clone())
+        } else {
+            Expr::Gt(*column, values[i].clone())
+        };
+
+        for (j, (previous, _)) in keys[..i].iter().enumerate().rev() {
+            branch = Expr::Equal(*previous, values[j].clone()).and(branch);
+        }
+
+        filter = Some(match filter {
+            Some(filter) => filter.or(branch),
+            None => branch,
+        });
+    }
+
+    filter.expect("keys are never empty")
+}
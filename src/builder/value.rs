@@ -1,4 +1,8 @@
-use sqlx::{Sqlite, query::Query, sqlite::SqliteArguments};
+use crate::dialect::sqlite;
+use crate::error::BuildError;
+use sqlx::Sqlite;
+use sqlx::query::Query;
+use sqlx::sqlite::SqliteArguments;
 
 #[derive(Clone, Debug)]
 pub enum Value {
@@ -11,6 +15,7 @@ pub enum Value {
 }
 
 impl Value {
+    #[deprecated(note = "values are bound by `Backend::query`, which supports every dialect")]
     pub fn bind_to<'q, 'a>(
         self,
         query: Query<'q, Sqlite, SqliteArguments<'a>>,
@@ -19,22 +24,41 @@ impl Value {
         'q: 'a,
         'a: 'q,
     {
+        sqlite::bind(query, self)
+    }
+
+    /// Write the value as an SQL literal, for the few places a placeholder isn't accepted.
+    pub(crate) fn write_literal(&self, f: &mut String) -> Result<(), BuildError> {
         match self {
-            Value::Bool(v) => query.bind(v),
-            Value::String(v) => query.bind(v),
-            Value::Integer(v) => query.bind(v),
-            Value::Real(v) => query.bind(v),
-            Value::Blob(v) => query.bind(v),
-            Value::Null => query.bind::<Option<i32>>(None),
+            Value::Bool(v) => f.push_str(if *v { "TRUE" } else { "FALSE" }),
+            Value::String(v) => {
+                f.push('\'');
+                f.push_str(&v.replace('\'', "''"));
+                f.push('\'');
+            }
+            Value::Integer(v) => f.push_str(&v.to_string()),
+            // NaN and inf would be read as identifiers
+            Value::Real(v) if !v.is_finite() => return Err(BuildError::NonFiniteLiteral),
+            Value::Real(v) => f.push_str(&v.to_string()),
+            Value::Blob(v) => {
+                f.push_str("X'");
+                for byte in v {
+                    f.push_str(&format!("{byte:02X}"));
+                }
+                f.push('\'');
+            }
+            Value::Null => f.push_str("NULL"),
         }
+
+        Ok(())
     }
 }
 
 macro_rules! valuable {
     ($name:ident, $type:ty) => {
-        impl Into<Value> for $type {
-            fn into(self) -> Value {
-                Value::$name(self)
+        impl From<$type> for Value {
+            fn from(value: $type) -> Self {
+                Value::$name(value)
             }
         }
     };
@@ -48,9 +72,9 @@ valuable!(Blob, Vec<u8>);
 
 macro_rules! valuable_with_coerce {
     ($name:ident, $type:ty, $conv:ty) => {
-        impl Into<Value> for $type {
-            fn into(self) -> Value {
-                Value::$name(self as $conv)
+        impl From<$type> for Value {
+            fn from(value: $type) -> Self {
+                Value::$name(value as $conv)
             }
         }
     };
@@ -65,29 +89,29 @@ valuable_with_coerce!(Real, f32, f64);
 valuable_with_coerce!(Integer, u8, i64);
 valuable_with_coerce!(Integer, u16, i64);
 
-impl Into<Value> for &str {
-    fn into(self) -> Value {
-        Value::String(self.to_string())
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
     }
 }
 
-impl<T: Into<Value>> Into<Value> for Option<T> {
-    fn into(self) -> Value {
-        match self {
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        match value {
             Some(value) => value.into(),
             None => Value::Null,
         }
     }
 }
 
-impl Into<Value> for () {
-    fn into(self) -> Value {
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
         Value::Null
     }
 }
 
-impl Into<Value> for &[u8] {
-    fn into(self) -> Value {
-        Value::Blob(self.to_vec())
+impl From<&[u8]> for Value {
+    fn from(value: &[u8]) -> Self {
+        Value::Blob(value.to_vec())
     }
 }
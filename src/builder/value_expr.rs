@@ -0,0 +1,223 @@
+use super::expr::{Expr, Subquery, write_subquery};
+use super::fragment::Fragment;
+use super::value::Value;
+use super::writer::SqlWriter;
+use super::{QueryBuilder, Select};
+use crate::column::{Column, ColumnRef};
+use crate::error::BuildError;
+use std::borrow::Cow;
+use std::ops::{Add, Div, Mul, Sub};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Concat,
+}
+
+impl BinaryOperator {
+    fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOperator::Add => " + ",
+            BinaryOperator::Sub => " - ",
+            BinaryOperator::Mul => " * ",
+            BinaryOperator::Div => " / ",
+            BinaryOperator::Concat => " || ",
+        }
+    }
+}
+
+/// An expression that produces a value, for the right hand side of `SET` and for comparisons
+/// that go beyond a column and a literal, eg `"count" + excluded."count"`.
+///
+/// Values convert into expressions, and the arithmetic operators combine them:
+/// `ValueExpr::col(Counter::Count) + ValueExpr::excluded(Counter::Count)`.
+#[derive(Clone)]
+pub enum ValueExpr<'a> {
+    Value(Value),
+    Column(ColumnRef),
+    /// A column of the row that failed to insert, in an upsert's `DO UPDATE`.
+    Excluded(&'static str),
+    Binary(Box<ValueExpr<'a>>, BinaryOperator, Box<ValueExpr<'a>>),
+    Function(&'static str, Vec<ValueExpr<'a>>),
+    Case(Case<'a>),
+    /// A subquery that selects a single value.
+    Subquery(Subquery<'a>),
+    Raw(Cow<'a, str>),
+    Fragment(Fragment<'a>),
+}
+
+/// A `CASE WHEN ... THEN ... ELSE ... END` expression, started with [`ValueExpr::case`].
+#[derive(Clone)]
+pub struct Case<'a> {
+    branches: Vec<(Expr<'a, ColumnRef>, ValueExpr<'a>)>,
+    otherwise: Option<Box<ValueExpr<'a>>>,
+}
+
+impl<'a> Case<'a> {
+    pub fn when<C: Column, V: Into<ValueExpr<'a>>>(
+        mut self,
+        condition: Expr<'a, C>,
+        then: V,
+    ) -> Self {
+        self.branches.push((condition.erase(), then.into()));
+        self
+    }
+
+    /// Finish the expression with an `ELSE` branch.
+    pub fn otherwise<V: Into<ValueExpr<'a>>>(mut self, value: V) -> ValueExpr<'a> {
+        self.otherwise = Some(Box::new(value.into()));
+        ValueExpr::Case(self)
+    }
+
+    /// Finish the expression without an `ELSE` branch, so it is `NULL` when nothing matches.
+    pub fn end(self) -> ValueExpr<'a> {
+        ValueExpr::Case(self)
+    }
+
+    fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        if self.branches.is_empty() {
+            return Err(BuildError::EmptyCase);
+        }
+
+        w.push_str("CASE");
+        for (condition, then) in self.branches {
+            w.push_str(" WHEN ");
+            condition.write(w)?;
+            w.push_str(" THEN ");
+            then.write(w)?;
+        }
+
+        if let Some(otherwise) = self.otherwise {
+            w.push_str(" ELSE ");
+            otherwise.write(w)?;
+        }
+
+        w.push_str(" END");
+        Ok(())
+    }
+}
+
+impl<'a> ValueExpr<'a> {
+    pub fn col<C: Column>(column: C) -> Self {
+        ValueExpr::Column(column.to_ref())
+    }
+
+    pub fn excluded<C: Column>(column: C) -> Self {
+        ValueExpr::Excluded(column.to_col_name())
+    }
+
+    /// Call an SQL function, eg `ValueExpr::function("lower", vec![ValueExpr::col(User::Username)])`.
+    pub fn function(name: &'static str, args: Vec<ValueExpr<'a>>) -> Self {
+        ValueExpr::Function(name, args)
+    }
+
+    pub fn case() -> Case<'a> {
+        Case {
+            branches: Vec::new(),
+            otherwise: None,
+        }
+    }
+
+    pub fn subquery<D: Column + 'a>(query: QueryBuilder<'a, Select, D>) -> Self {
+        ValueExpr::Subquery(Subquery::new(query))
+    }
+
+    pub fn concat<R: Into<ValueExpr<'a>>>(self, other: R) -> Self {
+        self.binary(BinaryOperator::Concat, other)
+    }
+
+    fn binary<R: Into<ValueExpr<'a>>>(self, operator: BinaryOperator, other: R) -> Self {
+        ValueExpr::Binary(Box::new(self), operator, Box::new(other.into()))
+    }
+
+    pub fn eq<C: Column, R: Into<ValueExpr<'a>>>(self, other: R) -> Expr<'a, C> {
+        Expr::Compare(self, " = ", other.into())
+    }
+
+    pub fn ne<C: Column, R: Into<ValueExpr<'a>>>(self, other: R) -> Expr<'a, C> {
+        Expr::Compare(self, " != ", other.into())
+    }
+
+    pub fn gt<C: Column, R: Into<ValueExpr<'a>>>(self, other: R) -> Expr<'a, C> {
+        Expr::Compare(self, " > ", other.into())
+    }
+
+    pub fn gte<C: Column, R: Into<ValueExpr<'a>>>(self, other: R) -> Expr<'a, C> {
+        Expr::Compare(self, " >= ", other.into())
+    }
+
+    pub fn lt<C: Column, R: Into<ValueExpr<'a>>>(self, other: R) -> Expr<'a, C> {
+        Expr::Compare(self, " < ", other.into())
+    }
+
+    pub fn lte<C: Column, R: Into<ValueExpr<'a>>>(self, other: R) -> Expr<'a, C> {
+        Expr::Compare(self, " <= ", other.into())
+    }
+
+    pub(crate) fn write(self, w: &mut SqlWriter) -> Result<(), BuildError> {
+        match self {
+            ValueExpr::Value(value) => w.push_value(value)?,
+            ValueExpr::Column(column) => w.push_column(&column),
+            ValueExpr::Excluded(name) => w.push_excluded(name),
+            ValueExpr::Raw(raw) => w.push_str(&raw),
+            ValueExpr::Fragment(fragment) => fragment.write(w)?,
+            ValueExpr::Function(name, args) => {
+                w.push_str(name);
+                w.push('(');
+                let mut first = true;
+                for arg in args {
+                    if !first {
+                        w.push_str(", ");
+                    } else {
+                        first = false;
+                    }
+                    arg.write(w)?;
+                }
+                w.push(')');
+            }
+            ValueExpr::Case(case) => case.write(w)?,
+            ValueExpr::Subquery(subquery) => write_subquery(w, subquery)?,
+            ValueExpr::Binary(left, operator, right) => {
+                w.push('(');
+                left.write(w)?;
+                w.push_str(operator.as_str());
+                right.write(w)?;
+                w.push(')');
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<V: Into<Value>> From<V> for ValueExpr<'_> {
+    fn from(value: V) -> Self {
+        ValueExpr::Value(value.into())
+    }
+}
+
+impl<'a> From<Fragment<'a>> for ValueExpr<'a> {
+    fn from(fragment: Fragment<'a>) -> Self {
+        ValueExpr::Fragment(fragment)
+    }
+}
+
+macro_rules! binary_operator {
+    ($trait:ident, $method:ident, $operator:ident) => {
+        impl<'a, R: Into<ValueExpr<'a>>> $trait<R> for ValueExpr<'a> {
+            type Output = ValueExpr<'a>;
+
+            fn $method(self, other: R) -> ValueExpr<'a> {
+                self.binary(BinaryOperator::$operator, other)
+            }
+        }
+    };
+}
+
+binary_operator!(Add, add, Add);
+binary_operator!(Sub, sub, Sub);
+binary_operator!(Mul, mul, Mul);
+binary_operator!(Div, div, Div);
@@ -0,0 +1,73 @@
+use super::value::Value;
+use crate::column::Column;
+use crate::dialect::Dialect;
+use crate::error::BuildError;
+
+/// Accumulates the SQL and bound values for a query as it is rendered for a dialect.
+pub struct SqlWriter<'d> {
+    dialect: &'d dyn Dialect,
+    sql: String,
+    values: Vec<Value>,
+    // whether columns are written with their table name
+    pub(crate) qualify: bool,
+    // whether values are written as literals instead of being bound
+    pub(crate) inline_values: bool,
+}
+
+impl<'d> SqlWriter<'d> {
+    pub fn new(dialect: &'d dyn Dialect) -> Self {
+        Self {
+            dialect,
+            sql: String::new(),
+            values: Vec::new(),
+            qualify: false,
+            inline_values: false,
+        }
+    }
+
+    pub fn dialect(&self) -> &'d dyn Dialect {
+        self.dialect
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.sql.push(c);
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.sql.push_str(s);
+    }
+
+    /// Bind a value and write its placeholder.
+    pub fn push_value(&mut self, value: Value) -> Result<(), BuildError> {
+        if self.inline_values || (matches!(value, Value::Null) && !self.dialect.binds_null()) {
+            return value.write_literal(&mut self.sql);
+        }
+
+        self.values.push(value);
+        self.dialect
+            .write_placeholder(&mut self.sql, self.values.len());
+        Ok(())
+    }
+
+    pub fn push_identifier(&mut self, identifier: &str) {
+        self.dialect.write_identifier(&mut self.sql, identifier);
+    }
+
+    /// Write a column of the row an upsert failed to insert.
+    pub fn push_excluded(&mut self, name: &str) {
+        self.dialect.write_excluded(&mut self.sql, name);
+    }
+
+    /// Write a column, qualified with its table name if another table is in scope.
+    pub fn push_column<C: Column>(&mut self, column: &C) {
+        if self.qualify {
+            column.write_qualified(self);
+        } else {
+            column.write(self);
+        }
+    }
+
+    pub fn finish(self) -> (String, Vec<Value>) {
+        (self.sql, self.values)
+    }
+}
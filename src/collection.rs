@@ -1,6 +1,11 @@
 use crate::builder::expr::Expr;
+use crate::builder::pagination::Page;
 use crate::builder::{QueryBuilder, Select};
+use crate::column::ColumnExpr;
+use crate::dialect::Backend;
 use crate::entity::Entity;
+use crate::error::Error;
+use futures_core::Stream;
 
 pub struct Collection<RE: Entity> {
     filter: Expr<'static, RE::C>,
@@ -15,10 +20,11 @@ impl<RE: Entity> Collection<RE> {
         }
     }
 
-    pub async fn load_all<'e, E>(&self, executor: E) -> sqlx::Result<Vec<RE>>
+    pub async fn load_all<'e, E>(&self, executor: E) -> Result<Vec<RE>, Error>
     where
-        E: 'e + sqlx::Executor<'e, Database = sqlx::Sqlite>,
-        for<'r> RE: sqlx::FromRow<'r, sqlx::sqlite::SqliteRow>,
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        for<'r> RE: sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
     {
         RE::query()
             .filter(self.filter.clone())
@@ -26,6 +32,62 @@ impl<RE: Entity> Collection<RE> {
             .await
     }
 
+    /// Stream the related entities instead of loading them all into memory.
+    pub fn stream<'e, 'c: 'e, E>(
+        &self,
+        executor: E,
+    ) -> impl Stream<Item = Result<RE, Error>> + use<'e, RE, E>
+    where
+        E: 'e + sqlx::Executor<'c>,
+        E::Database: Backend,
+        RE: 'e,
+        for<'r> RE: sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
+    {
+        RE::query().filter(self.filter.clone()).fetch(executor)
+    }
+
+    /// Load one page of the related entities, ordered by primary key.
+    pub async fn fetch_page<'c, A, DB>(
+        &self,
+        conn: A,
+        page: i64,
+        per_page: i64,
+    ) -> Result<Page<RE>, Error>
+    where
+        A: sqlx::Acquire<'c, Database = DB>,
+        DB: Backend,
+        for<'x> &'x mut DB::Connection: sqlx::Executor<'x, Database = DB>,
+        RE: for<'r> sqlx::FromRow<'r, DB::Row>,
+        (i64,): for<'r> sqlx::FromRow<'r, DB::Row>,
+    {
+        let mut query = self.query();
+        for column in RE::primary_key() {
+            query = query.order_by(column.asc());
+        }
+
+        query.fetch_page(conn, page, per_page).await
+    }
+
+    /// Count the related entities without loading them.
+    pub async fn count<'e, E>(&self, executor: E) -> Result<i64, Error>
+    where
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        (i64,): for<'r> sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
+    {
+        self.query().count(executor).await
+    }
+
+    /// Check whether there are any related entities without loading them.
+    pub async fn exists<'e, E>(&self, executor: E) -> Result<bool, Error>
+    where
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        (bool,): for<'r> sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
+    {
+        self.query().exists(executor).await
+    }
+
     pub fn query<'a>(&self) -> QueryBuilder<'a, Select, RE::C> {
         RE::query().filter(self.filter.clone())
     }
@@ -1,22 +1,116 @@
-use crate::builder::{expr::Expr, ordering::ColumnOrdering, value::Value};
+use crate::builder::{
+    QueryBuilder, Select,
+    aggregate::{Aggregate, AggregateFunction},
+    expr::{Expr, Subquery},
+    ordering::ColumnOrdering,
+    value::Value,
+    writer::SqlWriter,
+};
 
 pub trait Column: Copy + Send + Sync {
-    fn to_col_name(&self) -> &str;
+    fn to_col_name(&self) -> &'static str;
 
-    fn write(&self, f: &mut String) {
-        f.push('"');
-        f.push_str(self.to_col_name());
-        f.push('"');
+    /// The table this column belongs to, used to qualify the column when a query joins multiple tables.
+    fn table_name(&self) -> Option<&'static str> {
+        None
     }
+
+    fn write(&self, w: &mut SqlWriter) {
+        w.push_identifier(self.to_col_name());
+    }
+
+    fn write_qualified(&self, w: &mut SqlWriter) {
+        if let Some(table) = self.table_name() {
+            w.push_identifier(table);
+            w.push('.');
+        }
+
+        self.write(w);
+    }
+
+    fn to_ref(&self) -> ColumnRef {
+        ColumnRef {
+            table: self.table_name(),
+            name: self.to_col_name(),
+            aggregate: None,
+        }
+    }
+}
+
+/// A column with its entity type erased, so columns from different tables can appear in the same expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnRef {
+    pub table: Option<&'static str>,
+    pub name: &'static str,
+    /// The aggregate function applied to the column, if it was erased from an [`Aggregate`].
+    pub aggregate: Option<AggregateFunction>,
 }
 
+impl ColumnRef {
+    fn write_with(&self, w: &mut SqlWriter, qualify: bool) {
+        let column = ColumnRef {
+            aggregate: None,
+            ..*self
+        };
+
+        match self.aggregate {
+            Some(function) => function.write_with(w, |w| column.write_with(w, qualify)),
+            None => {
+                if qualify && let Some(table) = self.table {
+                    w.push_identifier(table);
+                    w.push('.');
+                }
+
+                w.push_identifier(self.name);
+            }
+        }
+    }
+}
+
+impl Column for ColumnRef {
+    fn to_col_name(&self) -> &'static str {
+        self.name
+    }
+
+    fn table_name(&self) -> Option<&'static str> {
+        self.table
+    }
+
+    fn write(&self, w: &mut SqlWriter) {
+        self.write_with(w, false);
+    }
+
+    fn write_qualified(&self, w: &mut SqlWriter) {
+        self.write_with(w, true);
+    }
+}
+
+#[allow(clippy::wrong_self_convention)]
 pub trait ColumnExpr<'a, C: Column> {
     fn eq<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
+    fn ne<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
     fn gt<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
+    fn gte<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
     fn lt<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
+    fn lte<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
+    fn between<V: Into<Value>>(self, low: V, high: V) -> Expr<'a, C>;
     fn like<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
+    fn not_like<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
+    /// Case sensitive matching with `*` and `?` wildcards, only available on SQLite.
+    fn glob<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
+    /// Like `ne`, but treats `NULL` as a value so `NULL` is distinct from everything else.
+    fn is_distinct_from<V: Into<Value>>(self, value: V) -> Expr<'a, C>;
     fn in_list<V: Into<Value>>(self, values: Vec<V>) -> Expr<'a, C>;
+    fn not_in<V: Into<Value>>(self, values: Vec<V>) -> Expr<'a, C>;
+    fn in_subquery<D: Column + 'a>(self, query: QueryBuilder<'a, Select, D>) -> Expr<'a, C>;
     fn is_null(self) -> Expr<'a, C>;
+    fn is_not_null(self) -> Expr<'a, C>;
+    fn eq_col<D: Column>(self, other: D) -> Expr<'a, C>;
+    fn ne_col<D: Column>(self, other: D) -> Expr<'a, C>;
+    fn gt_col<D: Column>(self, other: D) -> Expr<'a, C>;
+    fn gte_col<D: Column>(self, other: D) -> Expr<'a, C>;
+    fn lt_col<D: Column>(self, other: D) -> Expr<'a, C>;
+    fn lte_col<D: Column>(self, other: D) -> Expr<'a, C>;
 
     fn asc(self) -> ColumnOrdering<C>;
     fn desc(self) -> ColumnOrdering<C>;
@@ -24,6 +118,13 @@ pub trait ColumnExpr<'a, C: Column> {
     fn asc_nulls_last(self) -> ColumnOrdering<C>;
     fn desc_nulls_first(self) -> ColumnOrdering<C>;
     fn desc_nulls_last(self) -> ColumnOrdering<C>;
+
+    fn count(self) -> Aggregate<C>;
+    fn count_distinct(self) -> Aggregate<C>;
+    fn sum(self) -> Aggregate<C>;
+    fn avg(self) -> Aggregate<C>;
+    fn min(self) -> Aggregate<C>;
+    fn max(self) -> Aggregate<C>;
 }
 
 impl<'a, C: Column> ColumnExpr<'a, C> for C {
@@ -31,27 +132,92 @@ impl<'a, C: Column> ColumnExpr<'a, C> for C {
         Expr::Equal(self, value.into())
     }
 
+    fn ne<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
+        Expr::NotEqual(self, value.into())
+    }
+
     fn gt<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
         Expr::Gt(self, value.into())
     }
 
+    fn gte<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
+        Expr::Gte(self, value.into())
+    }
+
     fn lt<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
         Expr::Lt(self, value.into())
     }
 
+    fn lte<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
+        Expr::Lte(self, value.into())
+    }
+
+    fn between<V: Into<Value>>(self, low: V, high: V) -> Expr<'a, C> {
+        Expr::Between(self, low.into(), high.into())
+    }
+
     fn like<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
         Expr::Like(self, value.into())
     }
 
+    fn not_like<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
+        Expr::NotLike(self, value.into())
+    }
+
+    fn glob<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
+        Expr::Glob(self, value.into())
+    }
+
+    fn is_distinct_from<V: Into<Value>>(self, value: V) -> Expr<'a, C> {
+        Expr::IsDistinctFrom(self, value.into())
+    }
+
     fn in_list<V: Into<Value>>(self, values: Vec<V>) -> Expr<'a, C> {
         let values = values.into_iter().map(|v| v.into()).collect();
         Expr::In(self, values)
     }
 
+    fn not_in<V: Into<Value>>(self, values: Vec<V>) -> Expr<'a, C> {
+        let values = values.into_iter().map(|v| v.into()).collect();
+        Expr::NotIn(self, values)
+    }
+
+    fn in_subquery<D: Column + 'a>(self, query: QueryBuilder<'a, Select, D>) -> Expr<'a, C> {
+        Expr::InSubquery(self, Subquery::new(query))
+    }
+
     fn is_null(self) -> Expr<'a, C> {
         Expr::Equal(self, Value::Null)
     }
 
+    fn is_not_null(self) -> Expr<'a, C> {
+        Expr::NotEqual(self, Value::Null)
+    }
+
+    fn eq_col<D: Column>(self, other: D) -> Expr<'a, C> {
+        Expr::ColumnCompare(self, " = ", other.to_ref())
+    }
+
+    fn ne_col<D: Column>(self, other: D) -> Expr<'a, C> {
+        Expr::ColumnCompare(self, " != ", other.to_ref())
+    }
+
+    fn gt_col<D: Column>(self, other: D) -> Expr<'a, C> {
+        Expr::ColumnCompare(self, " > ", other.to_ref())
+    }
+
+    fn gte_col<D: Column>(self, other: D) -> Expr<'a, C> {
+        Expr::ColumnCompare(self, " >= ", other.to_ref())
+    }
+
+    fn lt_col<D: Column>(self, other: D) -> Expr<'a, C> {
+        Expr::ColumnCompare(self, " < ", other.to_ref())
+    }
+
+    fn lte_col<D: Column>(self, other: D) -> Expr<'a, C> {
+        Expr::ColumnCompare(self, " <= ", other.to_ref())
+    }
+
     fn asc(self) -> ColumnOrdering<C> {
         ColumnOrdering::Asc(self)
     }
@@ -75,4 +241,28 @@ impl<'a, C: Column> ColumnExpr<'a, C> for C {
     fn desc_nulls_last(self) -> ColumnOrdering<C> {
         ColumnOrdering::DescNullsLast(self)
     }
+
+    fn count(self) -> Aggregate<C> {
+        Aggregate::Count(self)
+    }
+
+    fn count_distinct(self) -> Aggregate<C> {
+        Aggregate::CountDistinct(self)
+    }
+
+    fn sum(self) -> Aggregate<C> {
+        Aggregate::Sum(self)
+    }
+
+    fn avg(self) -> Aggregate<C> {
+        Aggregate::Avg(self)
+    }
+
+    fn min(self) -> Aggregate<C> {
+        Aggregate::Min(self)
+    }
+
+    fn max(self) -> Aggregate<C> {
+        Aggregate::Max(self)
+    }
 }
@@ -0,0 +1,106 @@
+use crate::builder::value::Value;
+use sqlx::query::Query;
+
+#[cfg(feature = "mysql")]
+mod mysql;
+#[cfg(feature = "postgres")]
+mod postgres;
+pub(crate) mod sqlite;
+
+#[cfg(feature = "mysql")]
+pub use mysql::MySql;
+#[cfg(feature = "postgres")]
+pub use postgres::Postgres;
+pub use sqlite::Sqlite;
+
+/// How a dialect spells an insert that updates or ignores conflicting rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UpsertStyle {
+    /// `ON CONFLICT (...) DO NOTHING` / `ON CONFLICT (...) DO UPDATE SET ...`
+    OnConflict,
+    /// `ON DUPLICATE KEY UPDATE ...`, which has no conflict target.
+    OnDuplicateKey,
+}
+
+/// The parts of SQL generation that differ between databases.
+pub trait Dialect: Send + Sync {
+    /// Write the placeholder for a bound value, where `index` counts from 1.
+    fn write_placeholder(&self, f: &mut String, index: usize);
+
+    fn write_identifier(&self, f: &mut String, identifier: &str) {
+        f.push('"');
+        f.push_str(&identifier.replace('"', "\"\""));
+        f.push('"');
+    }
+
+    /// Write a reference to a column of the row that conflicted in an upsert.
+    fn write_excluded(&self, f: &mut String, column: &str) {
+        f.push_str("excluded.");
+        self.write_identifier(f, column);
+    }
+
+    /// Whether `NULLS FIRST`/`NULLS LAST` can be used in `ORDER BY`.
+    /// When it can't, the ordering is emulated by sorting on `IS NULL` first.
+    fn supports_nulls_ordering(&self) -> bool {
+        true
+    }
+
+    fn supports_returning(&self) -> bool {
+        true
+    }
+
+    /// Whether inserts and updates accept `OR IGNORE`, `OR REPLACE` and the like.
+    fn supports_conflict_resolution(&self) -> bool {
+        false
+    }
+
+    /// Whether `GLOB` pattern matching is available.
+    fn supports_glob(&self) -> bool {
+        false
+    }
+
+    /// The operator for a null-safe inequality, or `None` to write `NOT (a <=> b)` instead.
+    fn distinct_from_operator(&self) -> Option<&'static str> {
+        Some(" IS DISTINCT FROM ")
+    }
+
+    /// Whether `NULL` can be bound like any other value. When it can't, it's written as a literal,
+    /// as a bound `NULL` still needs a type and would be rejected by columns of any other type.
+    fn binds_null(&self) -> bool {
+        true
+    }
+
+    fn upsert_style(&self) -> UpsertStyle {
+        UpsertStyle::OnConflict
+    }
+
+    /// How an insert that sets no columns, leaving every one to its default, is written.
+    fn default_values(&self) -> &'static str {
+        " DEFAULT VALUES"
+    }
+
+    /// The most values that can be bound in a single statement.
+    fn max_variables(&self) -> usize;
+}
+
+/// A sqlx database that kali can build and run queries for.
+pub trait Backend: sqlx::Database {
+    type Args<'q>: sqlx::IntoArguments<'q, Self>;
+
+    fn dialect() -> &'static dyn Dialect;
+
+    fn query<'q>(sql: &'q str, values: Vec<Value>) -> Query<'q, Self, Self::Args<'q>>;
+
+    fn rows_affected(result: &Self::QueryResult) -> u64;
+
+    /// Read a column of a row back into a [`Value`], for building pagination cursors.
+    fn read_value(row: &Self::Row, column: &str) -> Result<Value, sqlx::Error>;
+}
+
+#[cfg(any(feature = "postgres", feature = "mysql"))]
+fn unsupported_type(column: &str, type_name: &str) -> sqlx::Error {
+    sqlx::Error::ColumnDecode {
+        index: column.to_string(),
+        source: format!("{type_name} can't be read into a Value").into(),
+    }
+}
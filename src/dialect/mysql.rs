@@ -0,0 +1,99 @@
+use super::{Backend, Dialect, UpsertStyle, unsupported_type};
+use crate::builder::value::Value;
+use sqlx::mysql::{MySqlArguments, MySqlRow};
+use sqlx::query::Query;
+use sqlx::{Row, TypeInfo, ValueRef};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MySql;
+
+impl Dialect for MySql {
+    fn write_placeholder(&self, f: &mut String, _index: usize) {
+        f.push('?');
+    }
+
+    fn write_identifier(&self, f: &mut String, identifier: &str) {
+        f.push('`');
+        f.push_str(&identifier.replace('`', "``"));
+        f.push('`');
+    }
+
+    fn write_excluded(&self, f: &mut String, column: &str) {
+        f.push_str("VALUES(");
+        self.write_identifier(f, column);
+        f.push(')');
+    }
+
+    fn supports_nulls_ordering(&self) -> bool {
+        false
+    }
+
+    fn supports_returning(&self) -> bool {
+        false
+    }
+
+    fn distinct_from_operator(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn upsert_style(&self) -> UpsertStyle {
+        UpsertStyle::OnDuplicateKey
+    }
+
+    fn default_values(&self) -> &'static str {
+        " () VALUES ()"
+    }
+
+    fn max_variables(&self) -> usize {
+        u16::MAX as usize
+    }
+}
+
+impl Backend for sqlx::MySql {
+    type Args<'q> = MySqlArguments;
+
+    fn dialect() -> &'static dyn Dialect {
+        &MySql
+    }
+
+    fn query<'q>(sql: &'q str, values: Vec<Value>) -> Query<'q, Self, Self::Args<'q>> {
+        let mut query = sqlx::query(sql);
+        for value in values {
+            query = match value {
+                Value::Bool(v) => query.bind(v),
+                Value::String(v) => query.bind(v),
+                Value::Integer(v) => query.bind(v),
+                Value::Real(v) => query.bind(v),
+                Value::Blob(v) => query.bind(v),
+                Value::Null => query.bind::<Option<i64>>(None),
+            };
+        }
+
+        query
+    }
+
+    fn rows_affected(result: &Self::QueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn read_value(row: &MySqlRow, column: &str) -> Result<Value, sqlx::Error> {
+        let value = row.try_get_raw(column)?;
+        if value.is_null() {
+            return Ok(Value::Null);
+        }
+
+        let type_name = value.type_info().name().to_string();
+        Ok(match type_name.as_str() {
+            "BOOLEAN" => Value::Bool(row.try_get_unchecked(column)?),
+            "FLOAT" | "DOUBLE" => Value::Real(row.try_get_unchecked(column)?),
+            "CHAR" | "VARCHAR" | "TINYTEXT" | "TEXT" | "MEDIUMTEXT" | "LONGTEXT" | "ENUM" => {
+                Value::String(row.try_get_unchecked(column)?)
+            }
+            "BINARY" | "VARBINARY" | "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" => {
+                Value::Blob(row.try_get_unchecked(column)?)
+            }
+            name if name.contains("INT") => Value::Integer(row.try_get_unchecked(column)?),
+            _ => return Err(unsupported_type(column, &type_name)),
+        })
+    }
+}
@@ -0,0 +1,73 @@
+use super::{Backend, Dialect, unsupported_type};
+use crate::builder::value::Value;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::Query;
+use sqlx::{Row, TypeInfo, ValueRef};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Postgres;
+
+impl Dialect for Postgres {
+    fn write_placeholder(&self, f: &mut String, index: usize) {
+        f.push('$');
+        f.push_str(&index.to_string());
+    }
+
+    fn max_variables(&self) -> usize {
+        // the bind message counts parameters with a u16
+        u16::MAX as usize
+    }
+
+    fn binds_null(&self) -> bool {
+        false
+    }
+}
+
+impl Backend for sqlx::Postgres {
+    type Args<'q> = PgArguments;
+
+    fn dialect() -> &'static dyn Dialect {
+        &Postgres
+    }
+
+    fn query<'q>(sql: &'q str, values: Vec<Value>) -> Query<'q, Self, Self::Args<'q>> {
+        let mut query = sqlx::query(sql);
+        for value in values {
+            query = match value {
+                Value::Bool(v) => query.bind(v),
+                Value::String(v) => query.bind(v),
+                Value::Integer(v) => query.bind(v),
+                Value::Real(v) => query.bind(v),
+                Value::Blob(v) => query.bind(v),
+                // queries built by kali write NULL literally, this is only reached for values bound by hand
+                Value::Null => query.bind::<Option<i64>>(None),
+            };
+        }
+
+        query
+    }
+
+    fn rows_affected(result: &Self::QueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn read_value(row: &PgRow, column: &str) -> Result<Value, sqlx::Error> {
+        let value = row.try_get_raw(column)?;
+        if value.is_null() {
+            return Ok(Value::Null);
+        }
+
+        let type_name = value.type_info().name().to_string();
+        Ok(match type_name.as_str() {
+            "BOOL" => Value::Bool(row.try_get_unchecked(column)?),
+            "INT2" => Value::Integer(row.try_get_unchecked::<i16, _>(column)?.into()),
+            "INT4" => Value::Integer(row.try_get_unchecked::<i32, _>(column)?.into()),
+            "INT8" => Value::Integer(row.try_get_unchecked(column)?),
+            "FLOAT4" => Value::Real(row.try_get_unchecked::<f32, _>(column)?.into()),
+            "FLOAT8" => Value::Real(row.try_get_unchecked(column)?),
+            "TEXT" | "VARCHAR" | "BPCHAR" | "NAME" => Value::String(row.try_get_unchecked(column)?),
+            "BYTEA" => Value::Blob(row.try_get_unchecked(column)?),
+            _ => return Err(unsupported_type(column, &type_name)),
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use super::{Backend, Dialect};
+use crate::builder::SQLITE_MAX_VARIABLE_NUMBER;
+use crate::builder::value::Value;
+use sqlx::query::Query;
+use sqlx::sqlite::{SqliteArguments, SqliteRow};
+use sqlx::{Row, TypeInfo, ValueRef};
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sqlite;
+
+impl Dialect for Sqlite {
+    fn write_placeholder(&self, f: &mut String, _index: usize) {
+        f.push('?');
+    }
+
+    fn supports_conflict_resolution(&self) -> bool {
+        true
+    }
+
+    fn supports_glob(&self) -> bool {
+        true
+    }
+
+    // IS DISTINCT FROM only arrived in 3.39, IS NOT has always meant the same
+    fn distinct_from_operator(&self) -> Option<&'static str> {
+        Some(" IS NOT ")
+    }
+
+    fn max_variables(&self) -> usize {
+        SQLITE_MAX_VARIABLE_NUMBER
+    }
+}
+
+pub(crate) fn bind<'q>(
+    query: Query<'q, sqlx::Sqlite, SqliteArguments<'q>>,
+    value: Value,
+) -> Query<'q, sqlx::Sqlite, SqliteArguments<'q>> {
+    match value {
+        Value::Bool(v) => query.bind(v),
+        Value::String(v) => query.bind(v),
+        Value::Integer(v) => query.bind(v),
+        Value::Real(v) => query.bind(v),
+        Value::Blob(v) => query.bind(v),
+        Value::Null => query.bind::<Option<i32>>(None),
+    }
+}
+
+impl Backend for sqlx::Sqlite {
+    type Args<'q> = SqliteArguments<'q>;
+
+    fn dialect() -> &'static dyn Dialect {
+        &Sqlite
+    }
+
+    fn query<'q>(sql: &'q str, values: Vec<Value>) -> Query<'q, Self, Self::Args<'q>> {
+        values.into_iter().fold(sqlx::query(sql), bind)
+    }
+
+    fn rows_affected(result: &Self::QueryResult) -> u64 {
+        result.rows_affected()
+    }
+
+    fn read_value(row: &SqliteRow, column: &str) -> Result<Value, sqlx::Error> {
+        let value = row.try_get_raw(column)?;
+        if value.is_null() {
+            return Ok(Value::Null);
+        }
+
+        // any column can hold any type, so go by the stored value rather than the declaration
+        let type_name = value.type_info().name().to_string();
+        Ok(match type_name.as_str() {
+            "INTEGER" => Value::Integer(row.try_get_unchecked(column)?),
+            "REAL" => Value::Real(row.try_get_unchecked(column)?),
+            "BLOB" => Value::Blob(row.try_get_unchecked(column)?),
+            _ => Value::String(row.try_get_unchecked(column)?),
+        })
+    }
+}
@@ -8,7 +8,8 @@ pub trait Entity {
 
     fn table_name() -> &'static str;
     fn columns() -> &'static [Self::C];
-    fn primary_key() -> &'static Self::C;
+    /// The primary key columns, more than one for a composite key.
+    fn primary_key() -> &'static [Self::C];
 
     fn query<'a>() -> QueryBuilder<'a, Select, Self::C> {
         QueryBuilder::select_from(Self::table_name()).columns(Self::columns())
@@ -0,0 +1,146 @@
+use std::fmt;
+
+/// A query that can't be turned into valid SQL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// An `UPDATE` without any `SET` values.
+    EmptyUpdate,
+    /// An `UPDATE` or `DELETE` without a filter that wasn't marked as applying to `all_rows`.
+    Unfiltered,
+    /// An `INSERT` without any values.
+    EmptyInsert,
+    /// An `INSERT` using both `value` and `values_rows`.
+    MixedInsertValues,
+    /// A row passed to `values_rows` has a different number of values than there are columns.
+    RowLength { expected: usize, found: usize },
+    /// A multi-row insert binds so many values outside its rows that not even one row fits under
+    /// the dialect's variable limit.
+    TooManyVariables { reserved: usize, limit: usize },
+    /// A query combined into a compound select has its own `ORDER BY`, `LIMIT`, `OFFSET` or `WITH`,
+    /// which SQLite only allows on the compound select as a whole.
+    CompoundPartClause,
+    /// A `CASE` expression without any `WHEN` branches.
+    EmptyCase,
+    /// A placeholder in an `sql!` fragment has no argument, or an argument of the wrong kind.
+    FragmentArgument {
+        position: usize,
+        expected: &'static str,
+    },
+    /// An `sql!` fragment has more arguments than placeholders.
+    FragmentArgumentCount {
+        placeholders: usize,
+        arguments: usize,
+    },
+    /// Keyset pagination needs an `ORDER BY` made only of columns sorted `ASC` or `DESC`.
+    KeysetOrdering,
+    /// A pagination cursor that wasn't produced for this query.
+    InvalidCursor,
+    /// A keyset paginated query is ordered by a column that is `NULL` in a row a cursor points at.
+    NullCursorValue,
+    /// A NaN or infinite real in a place that needs a literal instead of a bound value, like the
+    /// `WHERE` of a partial index in a conflict clause.
+    NonFiniteLiteral,
+    /// The query uses a feature the target dialect doesn't have, eg `RETURNING` on MySQL.
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::EmptyUpdate => write!(f, "UPDATE requires at least one SET value"),
+            BuildError::Unfiltered => write!(
+                f,
+                "UPDATE and DELETE without a filter affect every row, call `all_rows` if that is intended"
+            ),
+            BuildError::EmptyInsert => write!(f, "INSERT requires at least one value"),
+            BuildError::MixedInsertValues => {
+                write!(f, "INSERT cannot use both `value` and `values_rows`")
+            }
+            BuildError::RowLength { expected, found } => {
+                write!(
+                    f,
+                    "row has {found} values but {expected} columns were given"
+                )
+            }
+            BuildError::TooManyVariables { reserved, limit } => write!(
+                f,
+                "INSERT binds {reserved} values besides its rows, leaving no room for a row under the limit of {limit}"
+            ),
+            BuildError::CompoundPartClause => write!(
+                f,
+                "ORDER BY, LIMIT, OFFSET and WITH apply to the whole compound select, set them on the first query"
+            ),
+            BuildError::EmptyCase => write!(f, "CASE requires at least one WHEN branch"),
+            BuildError::FragmentArgument { position, expected } => {
+                write!(
+                    f,
+                    "placeholder {position} of the fragment expects a {expected} argument"
+                )
+            }
+            BuildError::FragmentArgumentCount {
+                placeholders,
+                arguments,
+            } => write!(
+                f,
+                "fragment has {placeholders} placeholders but {arguments} arguments were given"
+            ),
+            BuildError::KeysetOrdering => write!(
+                f,
+                "keyset pagination requires ordering by at least one column, ASC or DESC without NULLS FIRST/LAST"
+            ),
+            BuildError::InvalidCursor => {
+                write!(f, "cursor is malformed or belongs to another query")
+            }
+            BuildError::NullCursorValue => write!(
+                f,
+                "keyset pagination requires NOT NULL ordering columns, but a cursor row has a NULL"
+            ),
+            BuildError::NonFiniteLiteral => {
+                write!(f, "NaN and infinite reals can't be written as SQL literals")
+            }
+            BuildError::Unsupported(feature) => {
+                write!(f, "{feature} is not supported by this database")
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+#[derive(Debug)]
+pub enum Error {
+    Build(BuildError),
+    Database(sqlx::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Build(e) => write!(f, "invalid query: {e}"),
+            Error::Database(e) => e.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Build(e) => Some(e),
+            Error::Database(e) => Some(e),
+        }
+    }
+}
+
+impl From<BuildError> for Error {
+    fn from(e: BuildError) -> Self {
+        Error::Build(e)
+    }
+}
+
+impl From<sqlx::Error> for Error {
+    fn from(e: sqlx::Error) -> Self {
+        Error::Database(e)
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -0,0 +1,95 @@
+//! Tracing for executed queries.
+//!
+//! Every query runs inside a `kali.query` span at `DEBUG` level that records the SQL, the query kind,
+//! the table, how many values were bound, the number of rows returned or affected, and how long it took.
+
+use crate::builder::value::Value;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::Span;
+use tracing::field::{Empty, debug, display};
+
+static LOG_VALUES: AtomicBool = AtomicBool::new(false);
+// in microseconds, with u64::MAX meaning there is no threshold
+static SLOW_QUERY_THRESHOLD: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Record bound values on query spans. They are redacted by default as they often hold user data.
+pub fn set_log_values(enabled: bool) {
+    LOG_VALUES.store(enabled, Ordering::Relaxed);
+}
+
+/// Log queries that take at least `threshold` at `WARN` with their SQL, kind and table, or stop
+/// doing so with `None`.
+pub fn set_slow_query_threshold(threshold: Option<Duration>) {
+    let micros = threshold.map_or(u64::MAX, |threshold| {
+        u64::try_from(threshold.as_micros()).unwrap_or(u64::MAX - 1)
+    });
+
+    SLOW_QUERY_THRESHOLD.store(micros, Ordering::Relaxed);
+}
+
+fn slow_query_threshold() -> Option<Duration> {
+    match SLOW_QUERY_THRESHOLD.load(Ordering::Relaxed) {
+        u64::MAX => None,
+        micros => Some(Duration::from_micros(micros)),
+    }
+}
+
+pub(crate) struct QueryTrace {
+    span: Span,
+    start: Instant,
+    // kept for the slow query warning, which is logged even when the span is filtered out
+    kind: &'static str,
+    table: String,
+    sql: String,
+}
+
+impl QueryTrace {
+    pub(crate) fn new(kind: &'static str, table: &str, sql: &str, values: &[Value]) -> Self {
+        let span = tracing::debug_span!(
+            "kali.query",
+            sql = sql,
+            kind = kind,
+            table = table,
+            value_count = values.len(),
+            values = Empty,
+            rows = Empty,
+            elapsed = Empty,
+        );
+
+        if LOG_VALUES.load(Ordering::Relaxed) {
+            span.record("values", debug(values));
+        } else if !values.is_empty() {
+            span.record("values", display("[redacted]"));
+        }
+
+        Self {
+            span,
+            start: Instant::now(),
+            kind,
+            table: table.to_string(),
+            sql: sql.to_string(),
+        }
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    pub(crate) fn finish(self, rows: u64) {
+        let elapsed = self.start.elapsed();
+        self.span.record("rows", rows);
+        self.span.record("elapsed", debug(elapsed));
+
+        if slow_query_threshold().is_some_and(|threshold| elapsed >= threshold) {
+            tracing::warn!(
+                parent: &self.span,
+                sql = self.sql,
+                kind = self.kind,
+                table = self.table,
+                ?elapsed,
+                "slow query"
+            );
+        }
+    }
+}
@@ -1,7 +1,10 @@
 pub mod builder;
 pub mod collection;
 pub mod column;
+pub mod dialect;
 pub mod entity;
+pub mod error;
+pub mod instrument;
 pub mod reference;
 
 pub use kali_macros::entity;
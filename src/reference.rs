@@ -1,6 +1,8 @@
 use crate::builder::expr::Expr;
 use crate::builder::{QueryBuilder, Select};
+use crate::dialect::Backend;
 use crate::entity::Entity;
+use crate::error::Error;
 
 pub struct Reference<RE: Entity> {
     filter: Expr<'static, RE::C>,
@@ -15,10 +17,11 @@ impl<RE: Entity> Reference<RE> {
         }
     }
 
-    pub async fn load<'e, E>(&self, executor: E) -> sqlx::Result<RE>
+    pub async fn load<'e, E>(&self, executor: E) -> Result<RE, Error>
     where
-        E: 'e + sqlx::Executor<'e, Database = sqlx::Sqlite>,
-        for<'r> RE: sqlx::FromRow<'r, sqlx::sqlite::SqliteRow>,
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        for<'r> RE: sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
     {
         RE::query()
             .filter(self.filter.clone())
@@ -26,6 +29,16 @@ impl<RE: Entity> Reference<RE> {
             .await
     }
 
+    /// Check whether there is a related entity without loading it.
+    pub async fn exists<'e, E>(&self, executor: E) -> Result<bool, Error>
+    where
+        E: 'e + sqlx::Executor<'e>,
+        E::Database: Backend,
+        (bool,): for<'r> sqlx::FromRow<'r, <E::Database as sqlx::Database>::Row>,
+    {
+        self.query().exists(executor).await
+    }
+
     pub fn query<'a>(&self) -> QueryBuilder<'a, Select, RE::C> {
         RE::query().filter(self.filter.clone())
     }
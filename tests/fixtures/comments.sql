@@ -0,0 +1,7 @@
+INSERT INTO comments (id, parent_id, content) VALUES
+    (1, NULL, 'first'),
+    (2, 1, 'no u'),
+    (3, 2, 'no u'),
+    (4, 1, 'ratio'),
+    (5, NULL, 'second'),
+    (6, 5, 'agreed');
\ No newline at end of file
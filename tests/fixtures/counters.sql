@@ -0,0 +1,4 @@
+INSERT INTO counters (id, name, count, archived) VALUES
+    (1, 'views', 10, 0),
+    (2, 'views', 3, 1),
+    (3, 'likes', 5, 0);
@@ -0,0 +1,16 @@
+INSERT INTO crews (id, name) VALUES
+    (1, 'rocinante'),
+    (2, 'canterbury');
+
+INSERT INTO crew_members (user_id, crew_id, role) VALUES
+    (1, 1, 'captain'),
+    (7, 1, 'engineer'),
+    (6, 1, 'mechanic'),
+    (1, 2, 'xo'),
+    (7, 2, 'engineer');
+
+INSERT INTO member_notes (id, user_id, crew_id, note) VALUES
+    (1, 1, 1, 'took the ship'),
+    (2, 1, 1, 'gave it back'),
+    (3, 1, 2, 'lost the ship'),
+    (4, 7, 1, 'fixed the drive');
\ No newline at end of file
@@ -0,0 +1,189 @@
+use kali::builder::QueryBuilder;
+use kali::column::ColumnExpr;
+use sqlx::SqlitePool;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+#[kali::entity("users")]
+#[derive(Debug, sqlx::FromRow)]
+struct User {
+    id: i64,
+    username: String,
+}
+
+/// Collects the fields recorded on spans and events, and the messages of events.
+#[derive(Clone, Default)]
+struct Recorder {
+    fields: Arc<Mutex<Vec<(String, String)>>>,
+    event_fields: Arc<Mutex<Vec<(String, String)>>>,
+    events: Arc<Mutex<Vec<String>>>,
+    next_id: Arc<AtomicU64>,
+    // the most verbose level that is recorded, everything when unset
+    max_level: Option<Level>,
+}
+
+struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0
+            .push((field.name().to_string(), format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push((field.name().to_string(), value.to_string()));
+    }
+}
+
+impl Recorder {
+    fn field(&self, name: &str) -> Option<String> {
+        let fields = self.fields.lock().unwrap();
+        fields
+            .iter()
+            .rev()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn event_field(&self, name: &str) -> Option<String> {
+        let fields = self.event_fields.lock().unwrap();
+        fields
+            .iter()
+            .rev()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.max_level
+            .is_none_or(|level| *metadata.level() <= level)
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        span.record(&mut FieldVisitor(&mut self.fields.lock().unwrap()));
+        Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &Id, values: &Record<'_>) {
+        values.record(&mut FieldVisitor(&mut self.fields.lock().unwrap()));
+    }
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Vec::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.event_fields
+            .lock()
+            .unwrap()
+            .extend(fields.iter().cloned());
+        let mut events = self.events.lock().unwrap();
+        events.extend(
+            fields
+                .into_iter()
+                .filter(|(name, _)| name == "message")
+                .map(|(_, value)| value),
+        );
+    }
+
+    fn enter(&self, _: &Id) {}
+
+    fn exit(&self, _: &Id) {}
+}
+
+// the settings are global, so everything runs in one test to keep them from racing
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn query_spans(pool: SqlitePool) -> anyhow::Result<()> {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+
+    let users: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(User::Id.lt(4))
+        .fetch_all(&pool)
+        .await?;
+
+    assert_eq!(users.len(), 3);
+    assert_eq!((users[0].id, users[0].username.as_str()), (1, "holden"));
+    assert_eq!(
+        recorder.field("sql").as_deref(),
+        Some(r#"SELECT "id", "username" FROM users WHERE "id" < ?"#)
+    );
+    assert_eq!(recorder.field("kind").as_deref(), Some("SELECT"));
+    assert_eq!(recorder.field("table").as_deref(), Some("users"));
+    assert_eq!(recorder.field("value_count").as_deref(), Some("1"));
+    assert_eq!(recorder.field("values").as_deref(), Some("[redacted]"));
+    assert_eq!(recorder.field("rows").as_deref(), Some("3"));
+    assert!(recorder.field("elapsed").is_some());
+    assert!(recorder.events.lock().unwrap().is_empty());
+
+    kali::instrument::set_log_values(true);
+    kali::instrument::set_slow_query_threshold(Some(Duration::ZERO));
+
+    QueryBuilder::update("users")
+        .set(User::Username, "prax")
+        .filter(User::Id.eq(2))
+        .execute(&pool)
+        .await?;
+
+    kali::instrument::set_log_values(false);
+    kali::instrument::set_slow_query_threshold(None);
+
+    assert_eq!(recorder.field("kind").as_deref(), Some("UPDATE"));
+    assert_eq!(recorder.field("rows").as_deref(), Some("1"));
+    assert_eq!(
+        recorder.field("values").as_deref(),
+        Some(r#"[String("prax"), Integer(2)]"#)
+    );
+    assert_eq!(*recorder.events.lock().unwrap(), vec!["slow query"]);
+
+    QueryBuilder::select_from("users")
+        .columns(&[User::Id])
+        .filter(User::Id.eq(1))
+        .explain(&pool)
+        .await?;
+
+    assert_eq!(
+        recorder.field("sql").as_deref(),
+        Some(r#"EXPLAIN QUERY PLAN SELECT "id" FROM users WHERE "id" = ?"#)
+    );
+    assert_eq!(recorder.field("kind").as_deref(), Some("SELECT"));
+    assert_eq!(recorder.field("rows").as_deref(), Some("1"));
+
+    // the query span is at DEBUG, so a slow query logged under an INFO filter has to carry the
+    // query itself
+    let recorder = Recorder {
+        max_level: Some(Level::INFO),
+        ..Recorder::default()
+    };
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+    kali::instrument::set_slow_query_threshold(Some(Duration::ZERO));
+
+    let users: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(User::Id.eq(3))
+        .fetch_all(&pool)
+        .await?;
+    assert_eq!(users.len(), 1);
+
+    kali::instrument::set_slow_query_threshold(None);
+
+    assert!(recorder.fields.lock().unwrap().is_empty());
+    assert_eq!(*recorder.events.lock().unwrap(), vec!["slow query"]);
+    assert_eq!(
+        recorder.event_field("sql").as_deref(),
+        Some(r#"SELECT "id", "username" FROM users WHERE "id" = ?"#)
+    );
+    assert_eq!(recorder.event_field("kind").as_deref(), Some("SELECT"));
+    assert_eq!(recorder.event_field("table").as_deref(), Some("users"));
+    assert!(recorder.event_field("elapsed").is_some());
+
+    Ok(())
+}
@@ -0,0 +1,7 @@
+CREATE TABLE comments (
+    id INTEGER PRIMARY KEY,
+    parent_id INTEGER,
+    content TEXT NOT NULL,
+
+    FOREIGN KEY (parent_id) REFERENCES comments(id) ON DELETE CASCADE
+) STRICT;
@@ -0,0 +1,8 @@
+CREATE TABLE counters (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    archived INTEGER NOT NULL DEFAULT 0
+) STRICT;
+
+CREATE UNIQUE INDEX counters_active_name ON counters (name) WHERE archived = 0;
@@ -0,0 +1,23 @@
+CREATE TABLE crews (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+) STRICT;
+
+CREATE TABLE crew_members (
+    user_id INTEGER NOT NULL,
+    crew_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+
+    PRIMARY KEY (user_id, crew_id),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (crew_id) REFERENCES crews(id) ON DELETE CASCADE
+) STRICT;
+
+CREATE TABLE member_notes (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    crew_id INTEGER NOT NULL,
+    note TEXT NOT NULL,
+
+    FOREIGN KEY (user_id, crew_id) REFERENCES crew_members(user_id, crew_id) ON DELETE CASCADE
+) STRICT;
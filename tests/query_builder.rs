@@ -1,16 +1,51 @@
+use futures_util::TryStreamExt;
 use kali::{
-    builder::{OnConflict, QueryBuilder},
-    column::ColumnExpr,
+    builder::{
+        ConflictResolution, Delete, Insert, OnConflict, QueryBuilder, Upsert,
+        aggregate::Aggregate,
+        expr::Expr,
+        pagination::{Cursor, CursorPage, Page},
+        value::Value,
+        value_expr::ValueExpr,
+    },
+    column::{ColumnExpr, ColumnRef},
+    error::BuildError,
+    sql,
 };
-use sqlx::SqlitePool;
+use sqlx::{FromRow, SqlitePool};
 
-#[kali::entity]
+#[kali::entity("users")]
 #[derive(Debug, sqlx::FromRow)]
 struct User {
     id: i64,
     username: String,
 }
 
+#[kali::entity("posts")]
+#[derive(Debug, sqlx::FromRow)]
+struct Post {
+    id: i64,
+    user_id: i64,
+    title: String,
+}
+
+#[kali::entity("comments")]
+#[derive(Debug, sqlx::FromRow)]
+struct Comment {
+    id: i64,
+    parent_id: Option<i64>,
+    content: String,
+}
+
+#[kali::entity("counters")]
+#[derive(Debug, sqlx::FromRow)]
+struct Counter {
+    id: i64,
+    name: String,
+    count: i64,
+    archived: i64,
+}
+
 #[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
 async fn select_from(pool: SqlitePool) -> anyhow::Result<()> {
     let user: User = QueryBuilder::select_from("users")
@@ -25,6 +60,167 @@ async fn select_from(pool: SqlitePool) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "comments"))]
+async fn comparison_operators(pool: SqlitePool) -> anyhow::Result<()> {
+    async fn user_ids(pool: &SqlitePool, filter: Expr<'_, UserColumn>) -> anyhow::Result<Vec<i64>> {
+        let users: Vec<User> = QueryBuilder::select_from("users")
+            .columns(&[User::Id, User::Username])
+            .filter(filter)
+            .order_by(User::Id.asc())
+            .fetch_all(pool)
+            .await?;
+
+        Ok(users.into_iter().map(|user| user.id).collect())
+    }
+
+    assert_eq!(
+        user_ids(&pool, User::Id.ne(1)).await?,
+        vec![2, 3, 4, 5, 6, 7]
+    );
+    assert_eq!(user_ids(&pool, User::Id.gte(6)).await?, vec![6, 7]);
+    assert_eq!(user_ids(&pool, User::Id.lte(2)).await?, vec![1, 2]);
+    assert_eq!(
+        user_ids(&pool, User::Id.between(2, 4)).await?,
+        vec![2, 3, 4]
+    );
+    assert_eq!(
+        user_ids(&pool, User::Id.not_in(vec![1, 2, 3])).await?,
+        vec![4, 5, 6, 7]
+    );
+    assert_eq!(
+        user_ids(&pool, User::Username.not_like("%o%")).await?,
+        vec![2, 4, 5]
+    );
+    assert_eq!(
+        user_ids(&pool, User::Username.glob("[a-d]*")).await?,
+        vec![2, 3, 4, 6]
+    );
+    assert_eq!(user_ids(&pool, !User::Id.lt(6)).await?, vec![6, 7]);
+
+    let (sql, values) = QueryBuilder::select_from("users")
+        .filter(!User::Id.between(2, 4).or(User::Id.not_in(vec![6, 7])))
+        .to_sql()?;
+    assert_eq!(
+        sql,
+        r#"SELECT * FROM users WHERE NOT (("id" BETWEEN ? AND ?) OR ("id" NOT IN (?, ?)))"#
+    );
+    assert_eq!(values.len(), 4);
+
+    // unlike `ne`, `is_distinct_from` matches rows where the column is NULL
+    let comments: Vec<Comment> = QueryBuilder::select_from("comments")
+        .filter(Comment::ParentId.is_distinct_from(1))
+        .order_by(Comment::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+    let ids: Vec<i64> = comments.iter().map(|comment| comment.id).collect();
+    assert_eq!(ids, vec![1, 3, 5, 6]);
+
+    let comments: Vec<Comment> = QueryBuilder::select_from("comments")
+        .filter(Comment::ParentId.ne(1))
+        .order_by(Comment::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+    let ids: Vec<i64> = comments.iter().map(|comment| comment.id).collect();
+    assert_eq!(ids, vec![3, 6]);
+
+    let comments: Vec<Comment> = QueryBuilder::select_from("comments")
+        .filter(Comment::ParentId.is_not_null())
+        .order_by(Comment::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+    let ids: Vec<i64> = comments.iter().map(|comment| comment.id).collect();
+    assert_eq!(ids, vec![2, 3, 4, 6]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn column_comparisons(pool: SqlitePool) -> anyhow::Result<()> {
+    async fn post_ids(pool: &SqlitePool, filter: Expr<'_, PostColumn>) -> anyhow::Result<Vec<i64>> {
+        let posts: Vec<Post> = QueryBuilder::select_from("posts")
+            .filter(filter)
+            .order_by(Post::Id.asc())
+            .fetch_all(pool)
+            .await?;
+
+        Ok(posts.into_iter().map(|post| post.id).collect())
+    }
+
+    assert_eq!(
+        post_ids(&pool, Post::UserId.ne_col(Post::Id)).await?,
+        vec![3, 5, 6]
+    );
+    assert_eq!(
+        post_ids(&pool, Post::UserId.gt_col(Post::Id)).await?,
+        vec![5, 6]
+    );
+    assert_eq!(
+        post_ids(&pool, Post::UserId.gte_col(Post::Id)).await?,
+        vec![1, 2, 4, 5, 6, 7]
+    );
+    assert_eq!(
+        post_ids(&pool, Post::UserId.lt_col(Post::Id)).await?,
+        vec![3]
+    );
+    assert_eq!(
+        post_ids(&pool, Post::UserId.lte_col(Post::Id)).await?,
+        vec![1, 2, 3, 4, 7]
+    );
+
+    // columns of another entity are qualified even when nothing is joined
+    let (sql, _) = QueryBuilder::select_from("posts")
+        .filter(
+            Post::UserId
+                .eq_col(User::Id)
+                .and(Post::Id.gt_col(Post::UserId)),
+        )
+        .to_sql()?;
+    assert_eq!(
+        sql,
+        r#"SELECT * FROM posts WHERE ("posts"."user_id" = "users"."id") AND ("id" > "user_id")"#
+    );
+
+    let users: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(Expr::exists(QueryBuilder::select_from("posts").filter(
+            Post::UserId.eq_col(User::Id).and(Post::Id.gt_col(User::Id)),
+        )))
+        .order_by(User::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+    let ids: Vec<i64> = users.iter().map(|user| user.id).collect();
+    assert_eq!(ids, vec![2]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn fetch_stream(pool: SqlitePool) -> anyhow::Result<()> {
+    let users: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(User::Id.lt(4))
+        .order_by(User::Id.asc())
+        .fetch(&pool)
+        .try_collect()
+        .await?;
+
+    let ids: Vec<i64> = users.iter().map(|user| user.id).collect();
+    assert_eq!(ids, vec![1, 2, 3]);
+
+    // build errors are yielded by the stream instead of panicking
+    let result: Result<Vec<User>, _> = QueryBuilder::update("users")
+        .filter(User::Id.eq(1))
+        .fetch(&pool)
+        .try_collect()
+        .await;
+    assert!(matches!(
+        result,
+        Err(kali::error::Error::Build(BuildError::EmptyUpdate))
+    ));
+
+    Ok(())
+}
+
 #[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
 async fn insert_into(pool: SqlitePool) -> anyhow::Result<()> {
     QueryBuilder::insert_into("users")
@@ -73,6 +269,79 @@ async fn update(pool: SqlitePool) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[sqlx::test(
+    migrations = "tests/migrations",
+    fixtures("users", "posts", "counters")
+)]
+async fn update_expressions(pool: SqlitePool) -> anyhow::Result<()> {
+    let (sql, values) = QueryBuilder::update("counters")
+        .set_expr(Counter::Count, ValueExpr::col(Counter::Count) * 2 + 1)
+        .set_expr(
+            Counter::Name,
+            ValueExpr::function("upper", vec![ValueExpr::col(Counter::Name)]),
+        )
+        .set_expr(
+            Counter::Archived,
+            ValueExpr::case().when(Counter::Count.gt(4), 1).otherwise(0),
+        )
+        .filter(Counter::Id.eq(3))
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"UPDATE counters SET "count" = (("count" * ?) + ?), "name" = upper("name"), "archived" = CASE WHEN "count" > ? THEN ? ELSE ? END WHERE "id" = ?"#
+    );
+    assert_eq!(values.len(), 6);
+
+    QueryBuilder::update("counters")
+        .set_expr(Counter::Count, ValueExpr::col(Counter::Count) * 2 + 1)
+        .set_expr(
+            Counter::Name,
+            ValueExpr::function("upper", vec![ValueExpr::col(Counter::Name)]),
+        )
+        .set_expr(
+            Counter::Archived,
+            ValueExpr::case().when(Counter::Count.gt(4), 1).otherwise(0),
+        )
+        .filter(Counter::Id.eq(3))
+        .execute(&pool)
+        .await?;
+
+    QueryBuilder::update("counters")
+        .set_expr(
+            Counter::Count,
+            ValueExpr::subquery(
+                QueryBuilder::select_from("posts")
+                    .aggregate(Aggregate::count_all())
+                    .filter(Post::UserId.eq(7)),
+            ),
+        )
+        .filter(Counter::Id.eq(1))
+        .execute(&pool)
+        .await?;
+
+    let counters: Vec<Counter> = QueryBuilder::select_from("counters")
+        .columns(Counter::COLUMNS)
+        .filter(Counter::Id.in_list(vec![1, 3]))
+        .order_by(Counter::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+
+    let counters: Vec<_> = counters
+        .iter()
+        .map(|c| (c.id, c.name.as_str(), c.count, c.archived))
+        .collect();
+    assert_eq!(counters, vec![(1, "views", 3, 0), (3, "LIKES", 11, 1)]);
+
+    let result = QueryBuilder::update("counters")
+        .set_expr(Counter::Count, ValueExpr::case().end())
+        .all_rows()
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::EmptyCase);
+
+    Ok(())
+}
+
 #[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
 async fn delete(pool: SqlitePool) -> anyhow::Result<()> {
     let user: Option<User> = QueryBuilder::select_from("users")
@@ -98,6 +367,25 @@ async fn delete(pool: SqlitePool) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn all_rows(pool: SqlitePool) -> anyhow::Result<()> {
+    let result = QueryBuilder::<Delete, UserColumn>::delete_from("users")
+        .execute(&pool)
+        .await;
+    assert!(matches!(
+        result,
+        Err(kali::error::Error::Build(BuildError::Unfiltered))
+    ));
+
+    let result = QueryBuilder::<Delete, UserColumn>::delete_from("users")
+        .all_rows()
+        .execute(&pool)
+        .await?;
+    assert_eq!(result.rows_affected(), 7);
+
+    Ok(())
+}
+
 #[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
 async fn returning(pool: SqlitePool) -> anyhow::Result<()> {
     let user: User = QueryBuilder::insert_into("users")
@@ -114,6 +402,24 @@ async fn returning(pool: SqlitePool) -> anyhow::Result<()> {
 
 #[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
 async fn on_conflict(pool: SqlitePool) -> anyhow::Result<()> {
+    let user: User = QueryBuilder::insert_into("users")
+        .value(User::Id, 1)
+        .value(User::Username, "prax")
+        .on_conflict_do_update(User::Id)
+        .set(User::Username, "holden")
+        .returning(&[User::Id, User::Username])
+        .fetch_one(&pool)
+        .await?;
+
+    assert_eq!(user.id, 1);
+    assert_eq!(user.username, "holden");
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+#[allow(deprecated)]
+async fn on_conflict_deprecated(pool: SqlitePool) -> anyhow::Result<()> {
     let user: User = QueryBuilder::insert_into("users")
         .value(User::Id, 1)
         .value(User::Username, "prax")
@@ -122,9 +428,1245 @@ async fn on_conflict(pool: SqlitePool) -> anyhow::Result<()> {
         .returning(&[User::Id, User::Username])
         .fetch_one(&pool)
         .await?;
+    assert_eq!(user.username, "holden");
+
+    let result = QueryBuilder::insert_into("users")
+        .value(User::Id, 1)
+        .value(User::Username, "prax")
+        .on_conflict(User::Id, OnConflict::Ignore)
+        .execute(&pool)
+        .await?;
+    assert_eq!(result.rows_affected(), 0);
 
+    let result = QueryBuilder::insert_into("users")
+        .value(User::Id, 1)
+        .on_conflict(User::Id, OnConflict::Update)
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::EmptyUpdate);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+#[allow(deprecated)]
+async fn bind_to_deprecated(pool: SqlitePool) -> anyhow::Result<()> {
+    let (sql, values) = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(User::Username.eq("holden"))
+        .to_sql()?;
+
+    let query = values
+        .into_iter()
+        .fold(sqlx::query(&sql), |query, value| value.bind_to(query));
+    let user = User::from_row(&query.fetch_one(&pool).await?)?;
     assert_eq!(user.id, 1);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn on_conflict_do_nothing(pool: SqlitePool) -> anyhow::Result<()> {
+    let user: Option<User> = QueryBuilder::insert_into("users")
+        .value(User::Id, 1)
+        .value(User::Username, "prax")
+        .on_conflict_do_nothing(User::Id)
+        .returning(&[User::Id, User::Username])
+        .fetch_optional(&pool)
+        .await?;
+
+    assert!(user.is_none());
+
+    let user = User::fetch_one(&pool, 1).await?;
     assert_eq!(user.username, "holden");
 
     Ok(())
 }
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn conflict_resolution(pool: SqlitePool) -> anyhow::Result<()> {
+    let rows = ["holden", "prax", "naomi", "clarissa"]
+        .into_iter()
+        .map(|username| vec![username.into()]);
+    let result = QueryBuilder::insert_into("users")
+        .or(ConflictResolution::Ignore)
+        .values_rows(&[User::Username], rows)
+        .execute_many(&pool, true)
+        .await?;
+
+    // only "prax" and "clarissa" are new
+    assert_eq!(result.rows_affected(), 2);
+
+    let query = QueryBuilder::update("users")
+        .or(ConflictResolution::Ignore)
+        .set(User::Username, "holden")
+        .filter(User::Id.eq(2));
+    let (sql, _) = query.clone().to_sql()?;
+    assert_eq!(
+        sql,
+        r#"UPDATE OR IGNORE users SET "username" = ? WHERE "id" = ?"#
+    );
+
+    let result = query.execute(&pool).await?;
+    assert_eq!(result.rows_affected(), 0);
+
+    Ok(())
+}
+
+fn increment(count: i64) -> QueryBuilder<'static, Upsert, CounterColumn> {
+    QueryBuilder::insert_into("counters")
+        .value(Counter::Name, "views")
+        .value(Counter::Count, count)
+        .on_conflict_do_update_where(Counter::Name, Counter::Archived.eq(0))
+        .set_expr(
+            Counter::Count,
+            ValueExpr::col(Counter::Count) + ValueExpr::excluded(Counter::Count),
+        )
+        .update_where(ValueExpr::excluded(Counter::Count).gt(0))
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("counters"))]
+async fn upsert_expressions(pool: SqlitePool) -> anyhow::Result<()> {
+    let (sql, _) = increment(5).to_sql()?;
+    assert_eq!(
+        sql,
+        r#"INSERT INTO counters ("name", "count") VALUES (?, ?) ON CONFLICT ("name") WHERE "archived" = 0 DO UPDATE SET "count" = ("count" + excluded."count") WHERE excluded."count" > ?"#
+    );
+
+    increment(5).execute(&pool).await?;
+    // ignored by the update's WHERE
+    increment(-5).execute(&pool).await?;
+
+    let counters: Vec<Counter> = QueryBuilder::select_from("counters")
+        .columns(Counter::COLUMNS)
+        .filter(Counter::Name.eq("views"))
+        .order_by(Counter::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+
+    let counts: Vec<_> = counters
+        .iter()
+        .map(|c| (c.id, c.name.as_str(), c.count, c.archived))
+        .collect();
+    assert_eq!(counts, vec![(1, "views", 15, 0), (2, "views", 3, 1)]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn inner_join(pool: SqlitePool) -> anyhow::Result<()> {
+    let query = QueryBuilder::select_from("posts")
+        .columns(&[Post::Id, Post::UserId, Post::Title])
+        .inner_join("users", Post::UserId.eq_col(User::Id))
+        .filter_joined(User::Username.eq("naomi"))
+        .order_by(Post::Id.asc());
+
+    let posts: Vec<Post> = query.fetch_all(&pool).await?;
+    assert_eq!(posts.len(), 3);
+    assert_eq!(posts[0].id, 5);
+    assert_eq!(posts[0].title, "Foundation");
+    assert!(posts.iter().all(|p| p.user_id == 7));
+
+    let (sql, values) = QueryBuilder::select_from("posts")
+        .columns(&[Post::Id, Post::Title])
+        .inner_join("users", Post::UserId.eq_col(User::Id))
+        .filter_joined(User::Username.eq("naomi"))
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"SELECT "posts"."id", "posts"."title" FROM posts INNER JOIN users ON "posts"."user_id" = "users"."id" WHERE "users"."username" = ?"#
+    );
+    assert_eq!(values.len(), 1);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn left_join(pool: SqlitePool) -> anyhow::Result<()> {
+    // users without posts still come back from a left join, with NULL post columns
+    let users: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .left_join("posts", User::Id.eq_col(Post::UserId))
+        .filter_joined(Post::Id.is_null())
+        .order_by(User::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+
+    let ids: Vec<i64> = users.iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec![3, 5, 6]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn cross_join(pool: SqlitePool) -> anyhow::Result<()> {
+    let rows: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .cross_join("posts")
+        .fetch_all(&pool)
+        .await?;
+
+    assert_eq!(rows.len(), 7 * 7);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn group_by_having(pool: SqlitePool) -> anyhow::Result<()> {
+    let query = QueryBuilder::select_from("posts")
+        .columns(&[Post::UserId])
+        .aggregate(Post::Id.count())
+        .group_by(Post::UserId)
+        .having(Post::Id.count().gt(1))
+        .order_by(Post::UserId.asc());
+
+    let (sql, _) = QueryBuilder::select_from("posts")
+        .columns(&[Post::UserId])
+        .aggregate(Post::Id.count())
+        .group_by(Post::UserId)
+        .having(Post::Id.count().gt(1))
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"SELECT "user_id", COUNT("id") FROM posts GROUP BY "user_id" HAVING COUNT("id") > ?"#
+    );
+
+    let counts: Vec<(i64, i64)> = query.fetch_all(&pool).await?;
+    assert_eq!(counts, vec![(2, 2), (7, 3)]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn erased_aggregates(pool: SqlitePool) -> anyhow::Result<()> {
+    // aggregates keep their function when interpolated into fragments or compared to columns
+    let query = QueryBuilder::select_from("posts")
+        .columns(&[Post::UserId])
+        .group_by(Post::UserId)
+        .having(sql!("{} > ?", Post::Id.count(), 2).into())
+        .order_by_sql(sql!("{} DESC", Aggregate::<PostColumn>::count_all()));
+
+    let (sql, _) = query.clone().to_sql()?;
+    assert_eq!(
+        sql,
+        r#"SELECT "user_id" FROM posts GROUP BY "user_id" HAVING COUNT("id") > ? ORDER BY COUNT(*) DESC"#
+    );
+
+    let user_ids: Vec<(i64,)> = query.fetch_all(&pool).await?;
+    assert_eq!(user_ids, vec![(7,)]);
+
+    let (sql, _) = QueryBuilder::select_from("posts")
+        .columns(&[Post::UserId])
+        .group_by(Post::UserId)
+        .having(Post::Id.count_distinct().lt_col(Post::Id.max()))
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"SELECT "user_id" FROM posts GROUP BY "user_id" HAVING COUNT(DISTINCT "id") < MAX("id")"#
+    );
+
+    let (sql, values) = QueryBuilder::update("counters")
+        .set_expr(Counter::Count, ValueExpr::col(Post::Id.sum()) + 1)
+        .all_rows()
+        .to_sql()?;
+
+    assert_eq!(sql, r#"UPDATE counters SET "count" = (SUM("id") + ?)"#);
+    assert_eq!(values.len(), 1);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn aggregate_scalar(pool: SqlitePool) -> anyhow::Result<()> {
+    let count: i64 = QueryBuilder::select_from("posts")
+        .aggregate(Aggregate::<PostColumn>::count_all())
+        .fetch_scalar(&pool)
+        .await?;
+
+    assert_eq!(count, 7);
+
+    let (min, max): (i64, i64) = QueryBuilder::select_from("posts")
+        .aggregate(Post::Id.min())
+        .aggregate(Post::Id.max())
+        .filter(Post::UserId.eq(7))
+        .fetch_one(&pool)
+        .await?;
+
+    assert_eq!((min, max), (5, 7));
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn count_exists(pool: SqlitePool) -> anyhow::Result<()> {
+    let count = QueryBuilder::select_from("posts")
+        .columns(&[Post::Id, Post::Title])
+        .filter(Post::UserId.eq(7))
+        .order_by(Post::Id.desc())
+        .count(&pool)
+        .await?;
+
+    assert_eq!(count, 3);
+
+    // limited and grouped queries are counted as a subquery
+    let count = QueryBuilder::select_from("posts")
+        .filter(Post::UserId.eq(7))
+        .limit(2)
+        .count(&pool)
+        .await?;
+
+    assert_eq!(count, 2);
+
+    let count = QueryBuilder::select_from("posts")
+        .columns(&[Post::UserId])
+        .group_by(Post::UserId)
+        .count(&pool)
+        .await?;
+
+    assert_eq!(count, 4);
+
+    // HAVING without GROUP BY aggregates the matching rows into one
+    let count = QueryBuilder::select_from("posts")
+        .filter(Post::UserId.eq(7))
+        .having(Post::Id.count().gt(2))
+        .count(&pool)
+        .await?;
+
+    assert_eq!(count, 1);
+
+    let count = QueryBuilder::select_from("posts")
+        .filter(Post::UserId.eq(7))
+        .having(Post::Id.count().gt(3))
+        .count(&pool)
+        .await?;
+
+    assert_eq!(count, 0);
+
+    let exists = QueryBuilder::select_from("posts")
+        .filter(Post::UserId.eq(7))
+        .exists(&pool)
+        .await?;
+
+    assert!(exists);
+
+    let exists = QueryBuilder::select_from("posts")
+        .filter(Post::UserId.eq(3))
+        .exists(&pool)
+        .await?;
+
+    assert!(!exists);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn sql_fragments(pool: SqlitePool) -> anyhow::Result<()> {
+    let query = QueryBuilder::select_from("posts")
+        .columns(&[Post::Id])
+        .select_sql(sql!("upper({}) || ?", Post::Title, "!"))
+        .filter(Expr::from(sql!("length({}) > ?", Post::Title, 9)).and(Post::UserId.eq(7)))
+        .order_by_sql(sql!("length({}) DESC", Post::Title))
+        .order_by(Post::Id.asc());
+
+    let (sql, values) = query.clone().to_sql()?;
+    assert_eq!(
+        sql,
+        r#"SELECT "id", upper("title") || ? FROM posts WHERE (length("title") > ?) AND ("user_id" = ?) ORDER BY length("title") DESC, "id" ASC"#
+    );
+    assert_eq!(values.len(), 3);
+
+    let rows: Vec<(i64, String)> = query.fetch_all(&pool).await?;
+    assert_eq!(
+        rows,
+        vec![
+            (6, "NEUROMANCER!".to_string()),
+            (5, "FOUNDATION!".to_string()),
+            (7, "SNOW CRASH!".to_string()),
+        ]
+    );
+
+    // doubled characters are written literally
+    let (sql, _) = QueryBuilder::<_, PostColumn>::select_from("posts")
+        .filter(sql!("{} <> '{{??}}'", Post::Title).into())
+        .to_sql()?;
+    assert_eq!(sql, r#"SELECT * FROM posts WHERE "title" <> '{?}'"#);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn fetch_page(pool: SqlitePool) -> anyhow::Result<()> {
+    let ids = |page: &Page<User>| page.items.iter().map(|user| user.id).collect::<Vec<_>>();
+
+    let page: Page<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(User::Id.gt(2))
+        .order_by(User::Id.asc())
+        .fetch_page(&pool, 2, 2)
+        .await?;
+
+    assert_eq!(ids(&page), vec![5, 6]);
+    assert_eq!((page.total, page.page_count), (5, 3));
+    assert!(page.has_next && page.has_prev);
+
+    let page = User::fetch_page(&pool, 1, 3).await?;
+    assert_eq!(ids(&page), vec![1, 2, 3]);
+    assert_eq!(
+        (page.total, page.page, page.per_page, page.page_count),
+        (7, 1, 3, 3)
+    );
+    assert!(page.has_next && !page.has_prev);
+
+    let page = User::fetch_page(&pool, 3, 3).await?;
+    assert_eq!(ids(&page), vec![7]);
+    assert!(!page.has_next && page.has_prev);
+
+    let page = User::fetch_page(&pool, 4, 3).await?;
+    assert!(page.items.is_empty());
+    assert_eq!(page.total, 7);
+
+    // straight from a query string, so huge values must not overflow
+    let page = User::fetch_page(&pool, i64::MAX, i64::MAX).await?;
+    assert!(page.items.is_empty());
+    assert_eq!((page.total, page.page_count), (7, 1));
+    assert!(!page.has_next && page.has_prev);
+
+    let page: CursorPage<User> = QueryBuilder::select_from("users")
+        .order_by(User::Id.asc())
+        .fetch_cursor_page(&pool, i64::MAX)
+        .await?;
+    assert_eq!(page.items.len(), 7);
+    assert!(page.next.is_none());
+
+    Ok(())
+}
+
+#[sqlx::test(
+    migrations = "tests/migrations",
+    fixtures("users", "posts", "comments")
+)]
+async fn keyset_pagination(pool: SqlitePool) -> anyhow::Result<()> {
+    // mixed directions: 5, 6, 7, 4, 2, 3, 1
+    let query = || {
+        QueryBuilder::select_from("posts")
+            .order_by(Post::UserId.desc())
+            .order_by(Post::Id.asc())
+    };
+    let ids = |page: &CursorPage<Post>| page.items.iter().map(|post| post.id).collect::<Vec<_>>();
+
+    let first: CursorPage<Post> = query().fetch_cursor_page(&pool, 3).await?;
+    assert_eq!(ids(&first), vec![5, 6, 7]);
+    assert!(first.previous.is_none());
+
+    let next = first.next.unwrap();
+    let (sql, _) = query().paginate_after(&next).to_sql()?;
+    assert_eq!(
+        sql,
+        r#"SELECT * FROM posts WHERE ("user_id" < ?) OR (("user_id" = ?) AND ("id" > ?)) ORDER BY "user_id" DESC, "id" ASC"#
+    );
+
+    let second: CursorPage<Post> = query()
+        .paginate_after(&next)
+        .fetch_cursor_page(&pool, 3)
+        .await?;
+    assert_eq!(ids(&second), vec![4, 2, 3]);
+    let forward_next = second.next.clone();
+
+    let last: CursorPage<Post> = query()
+        .paginate_after(forward_next.as_ref().unwrap())
+        .fetch_cursor_page(&pool, 3)
+        .await?;
+    assert_eq!(ids(&last), vec![1]);
+    assert!(last.next.is_none());
+
+    // and back again
+    let second: CursorPage<Post> = query()
+        .paginate_before(last.previous.as_ref().unwrap())
+        .fetch_cursor_page(&pool, 3)
+        .await?;
+    assert_eq!(ids(&second), vec![4, 2, 3]);
+    assert_eq!(second.next, forward_next);
+
+    let first: CursorPage<Post> = query()
+        .paginate_before(second.previous.as_ref().unwrap())
+        .fetch_cursor_page(&pool, 3)
+        .await?;
+    assert_eq!(ids(&first), vec![5, 6, 7]);
+    assert!(first.previous.is_none());
+    assert_eq!(first.next.as_ref(), Some(&next));
+
+    // a single direction compares row values
+    let page: CursorPage<Post> = QueryBuilder::select_from("posts")
+        .order_by(Post::UserId.asc())
+        .order_by(Post::Id.asc())
+        .fetch_cursor_page(&pool, 2)
+        .await?;
+    let (sql, _) = QueryBuilder::<_, PostColumn>::select_from("posts")
+        .order_by(Post::UserId.asc())
+        .order_by(Post::Id.asc())
+        .paginate_after(page.next.as_ref().unwrap())
+        .to_sql()?;
+    assert_eq!(
+        sql,
+        r#"SELECT * FROM posts WHERE ("user_id", "id") > (?, ?) ORDER BY "user_id" ASC, "id" ASC"#
+    );
+
+    // counting applies the cursor before dropping the ordering
+    assert_eq!(query().paginate_after(&next).count(&pool).await?, 4);
+    assert_eq!(query().paginate_before(&next).count(&pool).await?, 2);
+    let page: Page<Post> = query()
+        .paginate_after(&next)
+        .fetch_page(&pool, 1, 3)
+        .await?;
+    assert_eq!(page.total, 4);
+    let page_ids = page.items.iter().map(|post| post.id).collect::<Vec<_>>();
+    assert_eq!(page_ids, vec![4, 2, 3]);
+
+    let result = QueryBuilder::select_from("comments")
+        .order_by(Comment::ParentId.asc())
+        .order_by(Comment::Id.asc())
+        .fetch_cursor_page::<_, Comment>(&pool, 3)
+        .await;
+    assert!(matches!(
+        result,
+        Err(kali::error::Error::Build(BuildError::NullCursorValue))
+    ));
+
+    let result = QueryBuilder::<_, CommentColumn>::select_from("comments")
+        .order_by(Comment::ParentId.asc())
+        .order_by(Comment::Id.asc())
+        // encodes (NULL, 1)
+        .paginate_after(&Cursor::from("6e690000000000000001"))
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::NullCursorValue);
+
+    let result = query()
+        .paginate_after(&Cursor::from("not a cursor"))
+        .fetch_cursor_page::<_, Post>(&pool, 3)
+        .await;
+    assert!(matches!(
+        result,
+        Err(kali::error::Error::Build(BuildError::InvalidCursor))
+    ));
+
+    let result = QueryBuilder::select_from("posts")
+        .order_by(Post::Id.asc_nulls_last())
+        .fetch_cursor_page::<_, Post>(&pool, 3)
+        .await;
+    assert!(matches!(
+        result,
+        Err(kali::error::Error::Build(BuildError::KeysetOrdering))
+    ));
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn in_subquery(pool: SqlitePool) -> anyhow::Result<()> {
+    let query = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(User::Id.gt(1))
+        .filter(
+            User::Id.in_subquery(
+                QueryBuilder::select_from("posts")
+                    .columns(&[Post::UserId])
+                    .filter(Post::Title.like("%e%")),
+            ),
+        )
+        .order_by(User::Id.asc());
+
+    let (sql, values) = QueryBuilder::select_from("users")
+        .columns(&[User::Id])
+        .filter(User::Id.gt(1))
+        .filter(
+            User::Id.in_subquery(
+                QueryBuilder::select_from("posts")
+                    .columns(&[Post::UserId])
+                    .filter(Post::Title.like("%e%")),
+            ),
+        )
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"SELECT "id" FROM users WHERE ("id" > ?) AND ("id" IN (SELECT "user_id" FROM posts WHERE "title" LIKE ?))"#
+    );
+    assert!(matches!(values.as_slice(), [Value::Integer(1), Value::String(s)] if s == "%e%"));
+
+    let users: Vec<User> = query.fetch_all(&pool).await?;
+    let ids: Vec<i64> = users.iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec![2, 4, 7]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn exists(pool: SqlitePool) -> anyhow::Result<()> {
+    let with_posts: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(Expr::exists(
+            QueryBuilder::select_from("posts").filter(Post::UserId.eq_col(User::Id)),
+        ))
+        .order_by(User::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+
+    let ids: Vec<i64> = with_posts.iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec![1, 2, 4, 7]);
+
+    let without_posts: Vec<User> = QueryBuilder::select_from("users")
+        .columns(&[User::Id, User::Username])
+        .filter(Expr::not_exists(
+            QueryBuilder::select_from("posts").filter(Post::UserId.eq_col(User::Id)),
+        ))
+        .order_by(User::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+
+    let ids: Vec<i64> = without_posts.iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec![3, 5, 6]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn values_rows(pool: SqlitePool) -> anyhow::Result<()> {
+    let (sql, values) = QueryBuilder::insert_into("users")
+        .values_rows(
+            &[User::Id, User::Username],
+            vec![
+                vec![8.into(), "prax".into()],
+                vec![9.into(), "clarissa".into()],
+            ],
+        )
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"INSERT INTO users ("id", "username") VALUES (?, ?), (?, ?)"#
+    );
+    assert_eq!(values.len(), 4);
+
+    QueryBuilder::insert_into("users")
+        .values_rows(
+            &[User::Id, User::Username],
+            vec![
+                vec![8.into(), "prax".into()],
+                vec![9.into(), "clarissa".into()],
+            ],
+        )
+        .execute(&pool)
+        .await?;
+
+    let user = User::fetch_one(&pool, 9).await?;
+    assert_eq!(user.username, "clarissa");
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn execute_many_chunks(pool: SqlitePool) -> anyhow::Result<()> {
+    let rows = (0..40_000).map(|i| vec![format!("user{i}").into()]);
+    let query = QueryBuilder::insert_into("users").values_rows(&[User::Username], rows);
+
+    let rows = (0..40_000).map(|i| vec![format!("user{i}").into()]);
+    let chunks = QueryBuilder::insert_into("users")
+        .values_rows(&[User::Username], rows)
+        .to_sql_chunks()?;
+    assert_eq!(chunks.len(), 2);
+    assert!(
+        chunks
+            .iter()
+            .all(|(_, values)| values.len() <= kali::builder::SQLITE_MAX_VARIABLE_NUMBER)
+    );
+
+    let result = query.execute_many(&pool, false).await?;
+    assert_eq!(result.rows_affected(), 40_000);
+
+    let count: i64 = QueryBuilder::select_from("users")
+        .aggregate(Aggregate::<UserColumn>::count_all())
+        .fetch_scalar(&pool)
+        .await?;
+    assert_eq!(count, 40_007);
+
+    Ok(())
+}
+
+#[test]
+fn chunks_reserve_bound_values() -> anyhow::Result<()> {
+    // exactly fills the limit on its own, but the update's WHERE binds one more value
+    let rows = (0..16_383).map(|i| vec![format!("counter{i}").into(), i.into()]);
+    let chunks = QueryBuilder::insert_into("counters")
+        .values_rows(&[Counter::Name, Counter::Count], rows)
+        .on_conflict_do_update(Counter::Name)
+        .set_expr(Counter::Count, ValueExpr::excluded(Counter::Count))
+        .update_where(Counter::Count.lt(100))
+        .to_sql_chunks()?;
+    assert_eq!(chunks.len(), 2);
+    assert!(
+        chunks
+            .iter()
+            .all(|(_, values)| values.len() <= kali::builder::SQLITE_MAX_VARIABLE_NUMBER)
+    );
+
+    // values bound in a CTE are repeated in every statement too
+    let ids = (0..kali::builder::SQLITE_MAX_VARIABLE_NUMBER as i64).collect::<Vec<_>>();
+    let result = QueryBuilder::insert_into("posts")
+        .with(
+            "banned",
+            QueryBuilder::select_from("users")
+                .columns(&[User::Id])
+                .filter(User::Id.in_list(ids)),
+        )
+        .values_rows(&[Post::Title], vec![vec!["title".into()]])
+        .to_sql_chunks();
+    assert_eq!(
+        result.unwrap_err(),
+        BuildError::TooManyVariables {
+            reserved: kali::builder::SQLITE_MAX_VARIABLE_NUMBER,
+            limit: kali::builder::SQLITE_MAX_VARIABLE_NUMBER,
+        }
+    );
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn execute_many_transaction(pool: SqlitePool) -> anyhow::Result<()> {
+    // the last row conflicts with an existing username, which fails the second statement
+    let rows = (0..40_000)
+        .map(|i| vec![format!("user{i}").into()])
+        .chain([vec!["holden".into()]]);
+
+    let result = QueryBuilder::insert_into("users")
+        .values_rows(&[User::Username], rows)
+        .execute_many(&pool, true)
+        .await;
+    assert!(result.is_err());
+
+    // the first statement was rolled back with it
+    let count: i64 = QueryBuilder::select_from("users")
+        .aggregate(Aggregate::<UserColumn>::count_all())
+        .fetch_scalar(&pool)
+        .await?;
+    assert_eq!(count, 7);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn with(pool: SqlitePool) -> anyhow::Result<()> {
+    let posts: Vec<Post> = QueryBuilder::select_from("naomi_posts")
+        .with(
+            "naomi_posts",
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::Id, Post::UserId, Post::Title])
+                .filter(Post::UserId.eq(7)),
+        )
+        .columns(&[Post::Id, Post::UserId, Post::Title])
+        .filter(Post::Title.like("%n%"))
+        .order_by(Post::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+
+    let ids: Vec<i64> = posts.iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![5, 6, 7]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn subquery_over_cte(pool: SqlitePool) -> anyhow::Result<()> {
+    // the subquery selects from the CTE, so its columns can't be qualified with "posts"
+    let query = QueryBuilder::select_from("users")
+        .with(
+            "recent",
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::UserId])
+                .filter(Post::Id.gt(4)),
+        )
+        .columns(&[User::Id, User::Username])
+        .filter(User::Id.in_subquery(QueryBuilder::select_from("recent").columns(&[Post::UserId])))
+        .order_by(User::Id.asc());
+
+    let (sql, _) = query.clone().to_sql()?;
+    assert_eq!(
+        sql,
+        r#"WITH "recent" AS (SELECT "user_id" FROM posts WHERE "id" > ?) SELECT "id", "username" FROM users WHERE "id" IN (SELECT "user_id" FROM recent) ORDER BY "id" ASC"#
+    );
+
+    let users: Vec<User> = query.fetch_all(&pool).await?;
+    let ids: Vec<i64> = users.iter().map(|u| u.id).collect();
+    assert_eq!(ids, vec![7]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("comments"))]
+async fn with_recursive(pool: SqlitePool) -> anyhow::Result<()> {
+    const THREAD_ID: ColumnRef = ColumnRef {
+        table: Some("thread"),
+        name: "id",
+        aggregate: None,
+    };
+
+    let query = QueryBuilder::select_from("thread")
+        .with_recursive(
+            "thread",
+            QueryBuilder::select_from("comments")
+                .columns(Comment::COLUMNS)
+                .filter(Comment::Id.eq(1)),
+            QueryBuilder::select_from("comments")
+                .columns(Comment::COLUMNS)
+                .inner_join("thread", Comment::ParentId.eq_col(THREAD_ID)),
+        )
+        .columns(Comment::COLUMNS)
+        .order_by(Comment::Id.asc());
+
+    let (sql, values) = QueryBuilder::select_from("thread")
+        .with_recursive(
+            "thread",
+            QueryBuilder::select_from("comments")
+                .columns(&[Comment::Id])
+                .filter(Comment::Id.eq(1)),
+            QueryBuilder::select_from("comments")
+                .columns(&[Comment::Id])
+                .inner_join("thread", Comment::ParentId.eq_col(THREAD_ID)),
+        )
+        .columns(&[Comment::Id])
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"WITH RECURSIVE "thread" AS (SELECT "id" FROM comments WHERE "id" = ? UNION ALL SELECT "comments"."id" FROM comments INNER JOIN thread ON "comments"."parent_id" = "thread"."id") SELECT "id" FROM thread"#
+    );
+    assert_eq!(values.len(), 1);
+
+    let comments: Vec<Comment> = query.fetch_all(&pool).await?;
+    let ids: Vec<i64> = comments.iter().map(|c| c.id).collect();
+    assert_eq!(ids, vec![1, 2, 3, 4]);
+    assert!(comments.iter().skip(1).all(|c| c.parent_id.is_some()));
+    assert_eq!(comments[0].content, "first");
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn compound_select(pool: SqlitePool) -> anyhow::Result<()> {
+    let (sql, values) = QueryBuilder::select_from("posts")
+        .columns(&[Post::Id])
+        .filter(Post::UserId.eq(7))
+        .union(
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::Id])
+                .filter(Post::Title.eq("Dune")),
+        )
+        .order_by(Post::Id.desc())
+        .limit(3)
+        .to_sql()?;
+
+    assert_eq!(
+        sql,
+        r#"SELECT "id" FROM posts WHERE "user_id" = ? UNION SELECT "id" FROM posts WHERE "title" = ? ORDER BY "id" DESC LIMIT 3"#
+    );
+    assert!(matches!(values.as_slice(), [Value::Integer(7), Value::String(s)] if s == "Dune"));
+
+    let posts: Vec<Post> = QueryBuilder::select_from("posts")
+        .columns(&[Post::Id, Post::UserId, Post::Title])
+        .filter(Post::UserId.eq(2))
+        .union_all(
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::Id, Post::UserId, Post::Title])
+                .filter(Post::Title.eq("Dune")),
+        )
+        .union_all(
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::Id, Post::UserId, Post::Title])
+                .filter(Post::Title.eq("Dune")),
+        )
+        .order_by(Post::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+
+    let ids: Vec<i64> = posts.iter().map(|p| p.id).collect();
+    assert_eq!(ids, vec![2, 3, 4, 4]);
+
+    let ids: Vec<(i64,)> = QueryBuilder::select_from("posts")
+        .columns(&[Post::UserId])
+        .except(
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::UserId])
+                .filter(Post::UserId.gt(2)),
+        )
+        .intersect(
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::UserId])
+                .filter(Post::UserId.lt(2)),
+        )
+        .fetch_all(&pool)
+        .await?;
+
+    assert_eq!(ids, vec![(1,)]);
+
+    // a compound nested in a compound keeps its own grouping
+    let query = QueryBuilder::select_from("posts")
+        .columns(&[Post::UserId])
+        .except(
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::UserId])
+                .filter(Post::UserId.gt(2))
+                .union(
+                    QueryBuilder::select_from("posts")
+                        .columns(&[Post::UserId])
+                        .filter(Post::UserId.eq(1)),
+                ),
+        );
+
+    let (sql, _) = query.clone().to_sql()?;
+    assert_eq!(
+        sql,
+        r#"SELECT "user_id" FROM posts EXCEPT SELECT * FROM (SELECT "user_id" FROM posts WHERE "user_id" > ? UNION SELECT "user_id" FROM posts WHERE "user_id" = ?) AS compounded"#
+    );
+
+    let ids: Vec<(i64,)> = query.fetch_all(&pool).await?;
+    assert_eq!(ids, vec![(2,)]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+async fn explain(pool: SqlitePool) -> anyhow::Result<()> {
+    let plan = QueryBuilder::select_from("users")
+        .columns(&[User::Id])
+        .filter(User::Username.eq("holden"))
+        .assert_indexed(&pool)
+        .await?;
+
+    assert!(plan.full_scans().is_empty());
+    assert!(
+        plan.nodes[0]
+            .detail
+            .starts_with("SEARCH users USING COVERING INDEX")
+    );
+
+    let plan = QueryBuilder::select_from("posts")
+        .columns(&[Post::Id])
+        .filter(Post::Title.eq("Foundation"))
+        .explain(&pool)
+        .await?;
+
+    let scans = plan.full_scans();
+    assert_eq!(scans.len(), 1);
+    assert_eq!(scans[0].scanned_table(), Some("posts"));
+
+    // walking a covering index still visits every row
+    let plan = QueryBuilder::select_from("users")
+        .columns(&[User::Username])
+        .filter(User::Username.like("%o%"))
+        .explain(&pool)
+        .await?;
+
+    let scans = plan.full_scans();
+    assert_eq!(scans.len(), 1);
+    assert!(
+        scans[0]
+            .detail
+            .starts_with("SCAN users USING COVERING INDEX")
+    );
+    assert_eq!(scans[0].scanned_table(), Some("users"));
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "posts"))]
+#[should_panic(expected = "full table scan: SCAN posts")]
+async fn assert_indexed_scan(pool: SqlitePool) {
+    QueryBuilder::select_from("posts")
+        .columns(&[Post::Id])
+        .filter(Post::Title.eq("Foundation"))
+        .assert_indexed(&pool)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+#[should_panic(expected = "full table scan: SCAN users USING COVERING INDEX")]
+async fn assert_indexed_index_scan(pool: SqlitePool) {
+    QueryBuilder::select_from("users")
+        .columns(&[User::Username])
+        .filter(User::Username.like("%o%"))
+        .assert_indexed(&pool)
+        .await
+        .unwrap();
+}
+
+#[test]
+fn build_errors() {
+    let result = QueryBuilder::update("users")
+        .filter(User::Id.eq(1))
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::EmptyUpdate);
+
+    let result = QueryBuilder::update("users")
+        .set(User::Username, "james")
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::Unfiltered);
+
+    let result = QueryBuilder::<Delete, UserColumn>::delete_from("users").to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::Unfiltered);
+
+    let result = QueryBuilder::<Insert, UserColumn>::insert_into("users").to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::EmptyInsert);
+
+    // rows without columns would render `INSERT INTO users () VALUES ()`
+    let result = QueryBuilder::<Insert, UserColumn>::insert_into("users")
+        .values_rows(&[], vec![vec![]])
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::EmptyInsert);
+
+    let result = QueryBuilder::<Insert, UserColumn>::insert_into("users")
+        .values_rows(&[], vec![vec![]])
+        .to_sql_chunks();
+    assert_eq!(result.unwrap_err(), BuildError::EmptyInsert);
+
+    let result = QueryBuilder::insert_into("users")
+        .values_rows(&[User::Id, User::Username], vec![vec![8.into()]])
+        .to_sql();
+    assert_eq!(
+        result.unwrap_err(),
+        BuildError::RowLength {
+            expected: 2,
+            found: 1
+        }
+    );
+
+    let result = QueryBuilder::select_from("users")
+        .columns(&[User::Id])
+        .union(
+            QueryBuilder::select_from("users")
+                .columns(&[User::Id])
+                .limit(1),
+        )
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::CompoundPartClause);
+
+    // the WHERE of a partial index is written with literals, which have no spelling for NaN
+    let result = QueryBuilder::insert_into("counters")
+        .value(Counter::Name, "views")
+        .on_conflict_do_nothing_where(Counter::Name, Counter::Count.lt(f64::NAN))
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::NonFiniteLiteral);
+
+    // errors in nested queries surface when the outer query is built
+    let result = QueryBuilder::select_from("users")
+        .columns(&[User::Id])
+        .filter(Expr::exists(
+            QueryBuilder::select_from("posts")
+                .columns(&[Post::Id])
+                .union(
+                    QueryBuilder::select_from("posts")
+                        .columns(&[Post::Id])
+                        .order_by(Post::Id.asc()),
+                ),
+        ))
+        .to_sql();
+    assert_eq!(result.unwrap_err(), BuildError::CompoundPartClause);
+
+    let result = QueryBuilder::<_, UserColumn>::select_from("users")
+        .filter(sql!("{} = ?", User::Id).into())
+        .to_sql();
+    assert_eq!(
+        result.unwrap_err(),
+        BuildError::FragmentArgument {
+            position: 2,
+            expected: "value"
+        }
+    );
+
+    let result = QueryBuilder::<_, UserColumn>::select_from("users")
+        .filter(sql!("{} = ?", 1, User::Id).into())
+        .to_sql();
+    assert_eq!(
+        result.unwrap_err(),
+        BuildError::FragmentArgument {
+            position: 1,
+            expected: "column"
+        }
+    );
+
+    let result = QueryBuilder::<_, UserColumn>::select_from("users")
+        .order_by_sql(sql!("random()", 1))
+        .to_sql();
+    assert_eq!(
+        result.unwrap_err(),
+        BuildError::FragmentArgumentCount {
+            placeholders: 0,
+            arguments: 1
+        }
+    );
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn build_error_on_execute(pool: SqlitePool) -> anyhow::Result<()> {
+    let result = QueryBuilder::update("users")
+        .filter(User::Id.eq(1))
+        .execute(&pool)
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(kali::error::Error::Build(BuildError::EmptyUpdate))
+    ));
+
+    Ok(())
+}
+
+#[cfg(feature = "postgres")]
+#[test]
+fn postgres_dialect() -> anyhow::Result<()> {
+    use kali::dialect::Postgres;
+
+    // placeholders are numbered across nested queries in the order they appear
+    let (sql, values) = QueryBuilder::select_from("users")
+        .columns(&[User::Id])
+        .filter(User::Username.eq("holden"))
+        .filter(
+            User::Id.in_subquery(
+                QueryBuilder::select_from("posts")
+                    .columns(&[Post::UserId])
+                    .filter(Post::Title.like("%e%")),
+            ),
+        )
+        .filter(User::Id.gt(1))
+        .to_sql_for(&Postgres)?;
+
+    assert_eq!(
+        sql,
+        r#"SELECT "id" FROM users WHERE (("username" = $1) AND ("id" IN (SELECT "user_id" FROM posts WHERE "title" LIKE $2))) AND ("id" > $3)"#
+    );
+    assert_eq!(values.len(), 3);
+
+    let (sql, _) = QueryBuilder::insert_into("users")
+        .value(User::Id, 1)
+        .value(User::Username, "naomi")
+        .on_conflict_do_update(User::Id)
+        .set(User::Username, "naomi")
+        .returning(&[User::Id])
+        .to_sql_for(&Postgres)?;
+
+    assert_eq!(
+        sql,
+        r#"INSERT INTO users ("id", "username") VALUES ($1, $2) ON CONFLICT ("id") DO UPDATE SET "username" = $3 RETURNING "id""#
+    );
+
+    // fragment placeholders are numbered like the rest of the query
+    let (sql, _) = QueryBuilder::select_from("users")
+        .filter(User::Id.gt(1))
+        .filter(sql!("{} ILIKE ?", User::Username, "n%").into())
+        .to_sql_for(&Postgres)?;
+
+    assert_eq!(
+        sql,
+        r#"SELECT * FROM users WHERE ("id" > $1) AND ("username" ILIKE $2)"#
+    );
+
+    let (sql, _) = QueryBuilder::select_from("comments")
+        .filter(Comment::ParentId.is_distinct_from(1))
+        .to_sql_for(&Postgres)?;
+    assert_eq!(
+        sql,
+        r#"SELECT * FROM comments WHERE "parent_id" IS DISTINCT FROM $1"#
+    );
+
+    // a bound NULL would be typed as whatever it was bound as, which most columns reject
+    let (sql, values) = QueryBuilder::update("comments")
+        .set(Comment::Content, "[deleted]")
+        .set(Comment::ParentId, None::<i64>)
+        .filter(Comment::Id.eq(2))
+        .to_sql_for(&Postgres)?;
+    assert_eq!(
+        sql,
+        r#"UPDATE comments SET "content" = $1, "parent_id" = NULL WHERE "id" = $2"#
+    );
+    assert_eq!(values.len(), 2);
+
+    Ok(())
+}
+
+#[cfg(feature = "mysql")]
+#[test]
+fn mysql_dialect() -> anyhow::Result<()> {
+    use kali::dialect::MySql;
+
+    let (sql, _) = QueryBuilder::select_from("comments")
+        .columns(&[Comment::Id])
+        .filter(Comment::Content.eq("hi"))
+        .order_by(Comment::ParentId.asc_nulls_last())
+        .to_sql_for(&MySql)?;
+
+    assert_eq!(
+        sql,
+        "SELECT `id` FROM comments WHERE `content` = ? ORDER BY `parent_id` IS NULL ASC, `parent_id` ASC"
+    );
+
+    let (sql, _) = QueryBuilder::insert_into("users")
+        .value(User::Id, 1)
+        .value(User::Username, "naomi")
+        .on_conflict_do_update(User::Id)
+        .set(User::Username, "naomi")
+        .to_sql_for(&MySql)?;
+
+    assert_eq!(
+        sql,
+        "INSERT INTO users (`id`, `username`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `username` = ?"
+    );
+
+    let (sql, _) = QueryBuilder::insert_into("users")
+        .value(User::Id, 1)
+        .on_conflict_do_nothing(User::Id)
+        .to_sql_for(&MySql)?;
+
+    assert_eq!(
+        sql,
+        "INSERT INTO users (`id`) VALUES (?) ON DUPLICATE KEY UPDATE `id` = `id`"
+    );
+
+    let (sql, _) = QueryBuilder::insert_into("counters")
+        .value(Counter::Name, "views")
+        .value(Counter::Count, 1)
+        .on_conflict_do_update(Counter::Name)
+        .set_expr(
+            Counter::Count,
+            ValueExpr::col(Counter::Count) + ValueExpr::excluded(Counter::Count),
+        )
+        .to_sql_for(&MySql)?;
+
+    assert_eq!(
+        sql,
+        "INSERT INTO counters (`name`, `count`) VALUES (?, ?) ON DUPLICATE KEY UPDATE `count` = (`count` + VALUES(`count`))"
+    );
+
+    let result = QueryBuilder::delete_from("users")
+        .filter(User::Id.eq(1))
+        .returning(&[User::Id])
+        .to_sql_for(&MySql);
+    assert_eq!(result.unwrap_err(), BuildError::Unsupported("RETURNING"));
+
+    let (sql, _) = QueryBuilder::select_from("comments")
+        .filter(Comment::ParentId.is_distinct_from(1))
+        .to_sql_for(&MySql)?;
+    assert_eq!(sql, "SELECT * FROM comments WHERE NOT (`parent_id` <=> ?)");
+
+    let result = QueryBuilder::select_from("users")
+        .filter(User::Username.glob("a*"))
+        .to_sql_for(&MySql);
+    assert_eq!(result.unwrap_err(), BuildError::Unsupported("GLOB"));
+
+    let (sql, _) = QueryBuilder::<_, CounterColumn>::insert_into("counters")
+        .default_values()
+        .to_sql_for(&MySql)?;
+    assert_eq!(sql, "INSERT INTO counters () VALUES ()");
+    Ok(())
+}
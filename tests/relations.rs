@@ -1,5 +1,7 @@
+use futures_util::TryStreamExt;
 use kali::{column::ColumnExpr, entity::Entity};
 use sqlx::SqlitePool;
+use std::pin::pin;
 
 #[kali::entity("users")]
 #[derive(Debug, sqlx::FromRow)]
@@ -37,6 +39,34 @@ struct Post {
     user: kali::reference::Reference<User>,
 }
 
+#[kali::entity("crew_members")]
+#[derive(Debug, sqlx::FromRow)]
+struct CrewMember {
+    #[primary_key]
+    user_id: i64,
+    #[primary_key]
+    crew_id: i64,
+    role: String,
+
+    #[relation(referenced_by = member)]
+    notes: kali::collection::Collection<MemberNote>,
+}
+
+#[kali::entity("member_notes")]
+#[derive(Debug, sqlx::FromRow)]
+struct MemberNote {
+    id: i64,
+    user_id: i64,
+    crew_id: i64,
+    note: String,
+
+    #[relation(foreign_key = (user_id, crew_id))]
+    member: kali::reference::Reference<CrewMember>,
+
+    #[relation(foreign_key = (crew_id, user_id), references = (crew_id, user_id))]
+    member_by_columns: kali::reference::Reference<CrewMember>,
+}
+
 #[sqlx::test(migrations = "tests/migrations", fixtures("users", "profiles"))]
 async fn test_user_profile_relation(pool: SqlitePool) -> anyhow::Result<()> {
     // Test fetching a user and then getting their profile
@@ -86,6 +116,58 @@ async fn test_user_posts_collection(pool: SqlitePool) -> anyhow::Result<()> {
     Ok(())
 }
 
+#[sqlx::test(
+    migrations = "tests/migrations",
+    fixtures("users", "profiles", "posts")
+)]
+async fn test_user_posts_stream(pool: SqlitePool) -> anyhow::Result<()> {
+    let user = User::fetch_one(&pool, 7).await?;
+
+    let mut ids = Vec::new();
+    let mut posts = pin!(user.posts().stream(&pool));
+    while let Some(post) = posts.try_next().await? {
+        assert_eq!(post.user_id, 7);
+        ids.push(post.id);
+    }
+
+    ids.sort();
+    assert_eq!(ids.len(), 3);
+    assert!(ids.contains(&5));
+
+    Ok(())
+}
+
+#[sqlx::test(
+    migrations = "tests/migrations",
+    fixtures("users", "profiles", "posts")
+)]
+async fn test_relation_count_exists(pool: SqlitePool) -> anyhow::Result<()> {
+    let user = User::fetch_one(&pool, 7).await?;
+    assert_eq!(user.posts().count(&pool).await?, 3);
+    assert!(user.posts().exists(&pool).await?);
+    assert!(user.profile().exists(&pool).await?);
+
+    let user = User::fetch_one(&pool, 2).await?;
+    assert!(!user.profile().exists(&pool).await?);
+
+    Ok(())
+}
+
+#[sqlx::test(
+    migrations = "tests/migrations",
+    fixtures("users", "profiles", "posts")
+)]
+async fn test_user_posts_page(pool: SqlitePool) -> anyhow::Result<()> {
+    let user = User::fetch_one(&pool, 7).await?;
+    let page = user.posts().fetch_page(&pool, 1, 2).await?;
+    let ids: Vec<i64> = page.items.iter().map(|post| post.id).collect();
+    assert_eq!(ids, vec![5, 6]);
+    assert_eq!((page.total, page.page_count), (3, 2));
+    assert!(page.has_next);
+
+    Ok(())
+}
+
 #[sqlx::test(
     migrations = "tests/migrations",
     fixtures("users", "profiles", "posts")
@@ -129,3 +211,58 @@ async fn test_query_with_relation_filter(pool: SqlitePool) -> anyhow::Result<()>
 
     Ok(())
 }
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "crews"))]
+async fn test_composite_primary_key(pool: SqlitePool) -> anyhow::Result<()> {
+    let member = CrewMember::fetch_one(&pool, (7, 2)).await?;
+    assert_eq!(member.role, "engineer");
+    assert_eq!(member.__primary_key_value(), (7, 2));
+    assert_eq!(
+        CrewMember::PRIMARY_KEYS,
+        &[CrewMember::UserId, CrewMember::CrewId]
+    );
+
+    assert!(CrewMember::fetch_optional(&pool, (6, 2)).await?.is_none());
+
+    let result = CrewMember::delete_one(&pool, (1, 2)).await?;
+    assert_eq!(result.rows_affected(), 1);
+    assert!(CrewMember::fetch_optional(&pool, (1, 2)).await?.is_none());
+    // only the row matching both columns is gone
+    assert!(CrewMember::fetch_optional(&pool, (1, 1)).await?.is_some());
+    assert!(CrewMember::fetch_optional(&pool, (7, 2)).await?.is_some());
+
+    let page = CrewMember::fetch_page(&pool, 1, 2).await?;
+    assert_eq!(page.total, 4);
+    let keys = page
+        .items
+        .iter()
+        .map(|m| m.__primary_key_value())
+        .collect::<Vec<_>>();
+    assert_eq!(keys, vec![(1, 1), (6, 1)]);
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "crews"))]
+async fn test_composite_key_relation(pool: SqlitePool) -> anyhow::Result<()> {
+    let member = CrewMember::fetch_one(&pool, (1, 1)).await?;
+    let notes: Vec<MemberNote> = member
+        .notes()
+        .query()
+        .order_by(MemberNote::Id.asc())
+        .fetch_all(&pool)
+        .await?;
+    let notes = notes.iter().map(|n| n.note.as_str()).collect::<Vec<_>>();
+    assert_eq!(notes, vec!["took the ship", "gave it back"]);
+
+    let note = MemberNote::fetch_one(&pool, 3).await?;
+    assert_eq!(note.id, 3);
+    let member = note.member().load(&pool).await?;
+    assert_eq!((member.user_id, member.crew_id), (1, 2));
+    assert_eq!(member.role, "xo");
+
+    let member = note.member_by_columns().load(&pool).await?;
+    assert_eq!(member.role, "xo");
+
+    Ok(())
+}
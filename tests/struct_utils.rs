@@ -1,14 +1,17 @@
-use kali::{column::ColumnExpr, entity::Entity};
+use kali::{builder::value::Value, column::ColumnExpr, entity::Entity};
 use sqlx::SqlitePool;
 
-#[kali::entity("users")]
+#[kali::entity("users", insertable, active)]
 #[derive(Debug, sqlx::FromRow)]
 struct User {
+    #[auto_increment]
     id: i64,
     username: String,
+    #[defaulted]
+    banned: i64,
 }
 
-#[kali::entity("user_profiles")]
+#[kali::entity("user_profiles", insertable)]
 #[derive(Debug, sqlx::FromRow)]
 struct UserProfile {
     #[primary_key]
@@ -16,6 +19,54 @@ struct UserProfile {
     bio: String,
 }
 
+// can't be converted into a Value, which only `insertable` and `active` entities need
+#[derive(Debug, PartialEq, sqlx::Type)]
+#[sqlx(rename_all = "lowercase")]
+enum Role {
+    Captain,
+    Engineer,
+    Mechanic,
+    Xo,
+}
+
+#[kali::entity("crew_members")]
+#[derive(Debug, sqlx::FromRow)]
+struct CrewMember {
+    #[primary_key]
+    user_id: i64,
+    #[primary_key]
+    crew_id: i64,
+    role: Role,
+}
+
+// implements neither Debug nor Clone, which NewCrew must not require
+#[derive(sqlx::Type)]
+#[sqlx(transparent)]
+struct ShipName(String);
+
+impl From<ShipName> for Value {
+    fn from(name: ShipName) -> Self {
+        Value::String(name.0)
+    }
+}
+
+#[kali::entity("crews", insertable)]
+#[derive(sqlx::FromRow)]
+struct Crew {
+    #[auto_increment]
+    id: i64,
+    name: ShipName,
+}
+
+#[kali::entity("events", insertable)]
+#[derive(Debug, sqlx::FromRow)]
+struct Event {
+    #[auto_increment]
+    id: i64,
+    #[defaulted]
+    created_at: String,
+}
+
 #[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
 async fn fetch_user_by_id(pool: SqlitePool) -> anyhow::Result<()> {
     let user = User::fetch_one(&pool, 1).await?;
@@ -48,3 +99,95 @@ async fn fetch_non_standard_pk(pool: SqlitePool) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn insert_new_entity(pool: SqlitePool) -> anyhow::Result<()> {
+    let user = NewUser {
+        username: "clarissa".to_string(),
+    }
+    .insert(&pool)
+    .await?;
+
+    // the id and the defaulted column come back from the database
+    assert_eq!(user.id, 8);
+    assert_eq!(user.username, "clarissa");
+    assert_eq!(user.banned, 0);
+
+    let profile = NewUserProfile {
+        user_id: user.id,
+        bio: "tightbeam".to_string(),
+    }
+    .insert(&pool)
+    .await?;
+
+    assert_eq!(profile.user_id, 8);
+    assert_eq!(UserProfile::fetch_one(&pool, 8).await?.bio, "tightbeam");
+
+    // nothing to set, so every column takes its default
+    let event = NewEvent {}.insert(&pool).await?;
+    assert_eq!(event.id, 1);
+    assert!(!event.created_at.is_empty());
+
+    let crew = NewCrew {
+        name: ShipName("agatha king".to_string()),
+    }
+    .insert(&pool)
+    .await?;
+    assert_eq!(crew.id, 1);
+    assert_eq!(crew.name.0, "agatha king");
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users"))]
+async fn save_changed_fields(pool: SqlitePool) -> anyhow::Result<()> {
+    let mut user = User::fetch_one(&pool, 1).await?.into_active();
+    assert_eq!(user.save(&pool).await?.rows_affected(), 0);
+
+    user.set_username("jim".to_string());
+    assert_eq!(user.changed(), &[UserColumn::Username]);
+    assert_eq!(user.username, "jim");
+
+    // written after the entity was loaded, and left alone as `banned` wasn't set
+    sqlx::query("UPDATE users SET banned = 1 WHERE id = 1")
+        .execute(&pool)
+        .await?;
+
+    let result = user.save(&pool).await?;
+    assert_eq!(result.rows_affected(), 1);
+    assert!(user.changed().is_empty());
+
+    let saved = User::fetch_one(&pool, 1).await?;
+    assert_eq!(saved.username, "jim");
+    assert_eq!(saved.banned, 1);
+
+    let mut user = saved.into_active();
+    user.set_banned(0).set_username("holden".to_string());
+    user.save(&pool).await?;
+
+    let saved = User::fetch_one(&pool, 1).await?;
+    assert_eq!((saved.username.as_str(), saved.banned), ("holden", 0));
+    assert_eq!(user.into_inner().username, "holden");
+
+    Ok(())
+}
+
+#[sqlx::test(migrations = "tests/migrations", fixtures("users", "crews"))]
+async fn fetch_entity_with_custom_type(pool: SqlitePool) -> anyhow::Result<()> {
+    let member = CrewMember::fetch_one(&pool, (1, 2)).await?;
+    assert_eq!((member.user_id, member.crew_id), (1, 2));
+    assert_eq!(member.role, Role::Xo);
+
+    let members: Vec<CrewMember> = CrewMember::query()
+        .filter(CrewMember::CrewId.eq(1))
+        .order_by(CrewMember::UserId.asc())
+        .fetch_all(&pool)
+        .await?;
+    let roles = members.iter().map(|m| &m.role).collect::<Vec<_>>();
+    assert_eq!(
+        roles,
+        vec![&Role::Captain, &Role::Mechanic, &Role::Engineer]
+    );
+
+    Ok(())
+}